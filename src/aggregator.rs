@@ -0,0 +1,276 @@
+use std::any::Any;
+#[cfg(feature = "async")]
+use std::panic::AssertUnwindSafe;
+
+#[cfg(feature = "async")]
+use futures_lite::FutureExt;
+
+use crate::{Client, Error};
+use crate::common::Function;
+use crate::common::models::{ActiveSearchResult, Release};
+use crate::dedup::{Deduplicator, Sourced};
+use crate::health::HealthStatus;
+
+/// A release found by an [`Aggregator`], tagged with the indexer it was taken from.
+#[derive(Debug, Clone)]
+pub struct AggregatedRelease {
+    /// Name of the indexer that returned this release first
+    pub indexer: String,
+    pub release: Release,
+    /// Names of other indexers that returned the same release
+    pub also_on: Vec<String>,
+}
+
+impl AsRef<Release> for AggregatedRelease {
+    fn as_ref(&self) -> &Release {
+        &self.release
+    }
+}
+
+impl Sourced for AggregatedRelease {
+    fn indexer(&self) -> Option<&str> {
+        Some(&self.indexer)
+    }
+}
+
+/// The outcome of a single indexer within an aggregated search.
+#[derive(Debug)]
+pub enum IndexerStatus {
+    /// The search succeeded and returned `items` of `total` results, if the indexer reported it
+    Searched { items: usize, total: Option<u64> },
+    /// The indexer reported in its caps that it does not support the search type
+    Unsupported,
+    Failed(Error),
+}
+
+#[derive(Debug)]
+pub struct IndexerReport {
+    pub indexer: String,
+    pub status: IndexerStatus,
+}
+
+impl IndexerReport {
+    pub fn is_success(&self) -> bool {
+        matches!(self.status, IndexerStatus::Searched { .. })
+    }
+}
+
+/// The merged results of an [`Aggregator`] search, and what happened on every indexer.
+#[derive(Debug)]
+pub struct AggregatedSearch {
+    pub releases: Vec<AggregatedRelease>,
+    pub reports: Vec<IndexerReport>,
+}
+
+impl AggregatedSearch {
+    pub fn failures(&self) -> impl Iterator<Item = &IndexerReport> {
+        self.reports.iter().filter(|r| matches!(r.status, IndexerStatus::Failed(_)))
+    }
+
+    /// Returns `true` if every capable indexer answered successfully.
+    pub fn is_complete(&self) -> bool {
+        self.failures().next().is_none()
+    }
+}
+
+/// Runs one query against many indexers and merges their results.
+///
+/// Indexers are kept in the order they were added, which is also their priority: when a
+/// release is returned by several indexers (see [`Deduplicator`]) the copy of the first one
+/// is kept.
+#[derive(Debug, Clone, Default)]
+pub struct Aggregator {
+    indexers: Vec<(String, Client)>,
+    dedup: Deduplicator,
+}
+
+impl Aggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(mut self, name: impl AsRef<str>, client: Client) -> Self {
+        self.push(name, client);
+        self
+    }
+
+    /// How duplicates are detected, [`Deduplicator::default`] if not set.
+    pub fn deduplicator(mut self, value: Deduplicator) -> Self {
+        self.dedup = value;
+        self
+    }
+
+    pub fn push(&mut self, name: impl AsRef<str>, client: Client) {
+        self.indexers.push((name.as_ref().to_string(), client));
+    }
+
+    pub fn indexers(&self) -> impl Iterator<Item = (&str, &Client)> {
+        self.indexers.iter().map(|(name, client)| (name.as_str(), client))
+    }
+
+    /// The health of every indexer with a [`HealthTracker`](crate::health::HealthTracker).
+    pub fn health(&self) -> Vec<(&str, HealthStatus)> {
+        self.indexers.iter()
+            .filter_map(|(name, client)| Some((name.as_str(), client.get_health()?.status())))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.indexers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indexers.is_empty()
+    }
+
+    /// Sends `f` to every indexer whose caps allow it and merges the results.
+    ///
+    /// Failing indexers do not fail the search, they are listed in
+    /// [`AggregatedSearch::reports`] instead.
+    #[maybe_async::maybe_async]
+    pub async fn search(&self, f: Function) -> AggregatedSearch {
+        let capable = self.indexers.iter()
+            .filter(|(_, client)| client.known_caps().supports(&f))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let results = Self::fan_out(capable, &f).await;
+
+        let mut reports = self.indexers.iter()
+            .filter(|(_, client)| !client.known_caps().supports(&f))
+            .map(|(name, _)| IndexerReport { indexer: name.clone(), status: IndexerStatus::Unsupported })
+            .collect::<Vec<_>>();
+
+        let mut found = Vec::new();
+        for (name, result) in results {
+            let status = match result {
+                Ok(res) => {
+                    let status = IndexerStatus::Searched { items: res.items.len(), total: res.search_offset.total };
+                    found.extend(res.items.into_iter().map(|item| AggregatedRelease {
+                        indexer: name.clone(),
+                        release: item.into(),
+                        also_on: vec![],
+                    }));
+                    status
+                }
+                Err(e) => {
+                    log::warn!("Indexer '{}' failed: {}", name, e);
+                    IndexerStatus::Failed(e)
+                }
+            };
+            reports.push(IndexerReport { indexer: name, status });
+        }
+
+        AggregatedSearch {
+            releases: merge(&self.dedup, found),
+            reports,
+        }
+    }
+
+    #[cfg(feature = "async")]
+    async fn fan_out(indexers: Vec<(String, Client)>, f: &Function) -> Vec<(String, Result<ActiveSearchResult, Error>)> {
+        let handles = indexers.into_iter()
+            .map(|(name, client)| {
+                let f = f.clone();
+                let search = AssertUnwindSafe(async move { client.search(f).await }).catch_unwind();
+                (name, async_std::task::spawn(search))
+            })
+            .collect::<Vec<_>>();
+
+        let mut results = Vec::with_capacity(handles.len());
+        for (name, handle) in handles {
+            let result = handle.await.unwrap_or_else(|panic| Err(panicked(panic)));
+            results.push((name, result));
+        }
+        results
+    }
+
+    #[cfg(feature = "sync")]
+    fn fan_out(indexers: Vec<(String, Client)>, f: &Function) -> Vec<(String, Result<ActiveSearchResult, Error>)> {
+        std::thread::scope(|scope| {
+            let handles = indexers.iter()
+                .map(|(name, client)| (name, scope.spawn(|| client.search(f.clone()))))
+                .collect::<Vec<_>>();
+
+            handles.into_iter()
+                .map(|(name, handle)| {
+                    let result = handle.join().unwrap_or_else(|panic| Err(panicked(panic)));
+                    (name.clone(), result)
+                })
+                .collect()
+        })
+    }
+}
+
+/// Keeps the first release of every cluster of duplicates, the indexers of the others are
+/// listed in its `also_on`.
+fn merge(dedup: &Deduplicator, releases: Vec<AggregatedRelease>) -> Vec<AggregatedRelease> {
+    dedup.cluster(releases).into_iter()
+        .map(|cluster| {
+            let mut merged = cluster.representative;
+            for other in cluster.alternates {
+                if other.indexer != merged.indexer && !merged.also_on.contains(&other.indexer) {
+                    merged.also_on.push(other.indexer);
+                }
+            }
+            merged
+        })
+        .collect()
+}
+
+/// The error reported for a search that panicked with `panic`.
+fn panicked(panic: Box<dyn Any + Send>) -> Error {
+    let message = panic.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+    log::error!("Search panicked: {}", message);
+    Error::Panicked(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Error;
+    use crate::aggregator::{merge, AggregatedRelease, Aggregator, IndexerStatus};
+    use crate::common::models::SearchQuery;
+    use crate::dedup::Deduplicator;
+    use crate::mock::{MockFailure, MockIndexer, MockRelease};
+
+    fn release(indexer: &str, title: &str, guid: &str, size: u64) -> AggregatedRelease {
+        let release = MockRelease::new(title).guid(guid).size(size).to_release();
+        AggregatedRelease { indexer: indexer.to_string(), release, also_on: vec![] }
+    }
+
+    #[test]
+    fn merges_by_guid_and_title_size() {
+        let releases = merge(&Deduplicator::new(), vec![
+            release("a", "Show.Name.S01E01.720p-GRP", "1", 100),
+            release("b", "Show Name S01E01 720p GRP", "2", 100),
+            release("b", "Show.Name.S01E01.1080p-GRP", "3", 200),
+            release("c", "Something else", "3", 5),
+            release("b", "Show.Name.S01E01.1080p-GRP.repost", "3", 200),
+        ]);
+
+        assert_eq!(releases.len(), 3);
+        assert_eq!(releases[0].indexer, "a");
+        assert_eq!(releases[0].also_on, vec!["b".to_string()]);
+        assert_eq!(releases[1].indexer, "b");
+        assert!(releases[1].also_on.is_empty());
+        assert_eq!(releases[2].indexer, "c");
+    }
+
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn reports_panicking_searches() {
+        let healthy = MockIndexer::new().release(MockRelease::new("Show.S01E01"));
+        let broken = MockIndexer::new().release(MockRelease::new("Show.S01E02"));
+        let aggregator = Aggregator::new().add("healthy", healthy.client()).add("broken", broken.client());
+        broken.fail_next(MockFailure::Panic);
+
+        let search = aggregator.search(SearchQuery::new("show").into()).await;
+        assert_eq!(search.releases.len(), 1);
+        let failures = search.failures().map(|r| (r.indexer.as_str(), &r.status)).collect::<Vec<_>>();
+        assert!(matches!(failures[..], [("broken", IndexerStatus::Failed(Error::Panicked(_)))]));
+    }
+}
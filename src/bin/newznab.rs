@@ -0,0 +1,357 @@
+//! `newznab`, a command line client to debug indexers, built with the `cli` feature.
+//!
+//! ```text
+//! newznab --config indexers.toml --indexer hydra search "one piece" --cat 5000 --format json
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use maybe_newznab_client::Client;
+use maybe_newznab_client::common::{Format, Function};
+use maybe_newznab_client::config::Config;
+use maybe_newznab_client::quirks::ServerSoftware;
+use maybe_newznab_client::common::models::{ActiveSearchResult, Caps, Release, SearchParameters, SearchQuery};
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Debug, Parser)]
+#[command(name = "newznab", version, about = "Query newznab indexers from the command line")]
+struct Cli {
+    /// TOML or JSON config file with the indexers, see the `config` module
+    #[arg(short, long, env = "NEWZNAB_CONFIG", default_value = "newznab.toml")]
+    config: PathBuf,
+    /// Name of the indexer to query, may be omitted if the config has only one
+    #[arg(short, long)]
+    indexer: Option<String>,
+    #[arg(short, long, value_enum, default_value_t = Output::Table)]
+    format: Output,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Output {
+    Table,
+    Json,
+    /// The unmodified response of the indexer
+    Xml,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Capabilities of the indexer
+    Caps,
+    /// Searches all categories
+    Search {
+        #[command(flatten)]
+        query: QueryArgs,
+    },
+    /// Searches TV releases, by name or by show id
+    Tvsearch {
+        #[command(flatten)]
+        query: QueryArgs,
+        #[arg(long)]
+        season: Option<String>,
+        #[arg(long)]
+        ep: Option<String>,
+        #[arg(long)]
+        tvdbid: Option<String>,
+        #[arg(long)]
+        rid: Option<String>,
+        #[arg(long)]
+        imdbid: Option<String>,
+    },
+    /// Searches movies, by name or by IMDb id
+    Movie {
+        #[command(flatten)]
+        query: QueryArgs,
+        /// IMDb id without the `tt` prefix
+        #[arg(long)]
+        imdbid: Option<String>,
+    },
+    /// Details of a release by its guid
+    Details { id: String },
+    /// Saves the NZB of a release
+    Get {
+        id: String,
+        /// Target file, `-` for stdout, defaults to `<id>.nzb`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Account information
+    User { username: String },
+}
+
+#[derive(Debug, Args)]
+struct QueryArgs {
+    #[arg(default_value = "")]
+    q: String,
+    /// Comma separated category ids
+    #[arg(long, value_delimiter = ',')]
+    cat: Vec<u32>,
+    #[arg(long)]
+    limit: Option<u32>,
+    #[arg(long)]
+    offset: Option<u64>,
+}
+
+impl QueryArgs {
+    fn to_query(&self) -> SearchQuery {
+        let mut query = SearchQuery::new(&self.q).categories(self.cat.iter().copied());
+        if let Some(limit) = self.limit {
+            query = query.limit(limit);
+        }
+        if let Some(offset) = self.offset {
+            query = query.offset(offset);
+        }
+        query
+    }
+}
+
+/// The client of the indexer `name`, which may be omitted if the config has only one.
+fn client(config: &Config, name: Option<&str>) -> CliResult<Client> {
+    match name {
+        Some(name) => Ok(config.client(name)?),
+        None if config.indexers.len() == 1 => Ok(config.client(config.indexers.keys().next().unwrap())?),
+        None => {
+            let names = config.indexers.keys().map(String::as_str).collect::<Vec<_>>().join(", ");
+            Err(format!("choose an indexer with --indexer: {}", names).into())
+        }
+    }
+}
+
+impl Command {
+    fn search_function(&self) -> Option<Function> {
+        match self {
+            Command::Search { query } => Some(Function::Search(query.to_query().build())),
+            Command::Tvsearch { query, season, ep, tvdbid, rid, imdbid } => Some(Function::TvSearch(
+                with_params(query.to_query(), &[("season", season), ("ep", ep), ("tvdbid", tvdbid), ("rid", rid), ("imdbid", imdbid)]),
+            )),
+            Command::Movie { query, imdbid } => Some(Function::Movie(
+                with_params(query.to_query(), &[("imdbid", imdbid)]),
+            )),
+            _ => None,
+        }
+    }
+}
+
+fn with_params(query: SearchQuery, params: &[(&str, &Option<String>)]) -> SearchParameters {
+    params.iter()
+        .filter_map(|(name, value)| Some((name, value.as_ref()?)))
+        .fold(query, |query, (name, value)| query.param(name, value))
+        .build()
+}
+
+#[maybe_async::maybe_async]
+async fn run(cli: Cli) -> CliResult<()> {
+    let config = Config::load(&cli.config).map_err(|e| format!("{}: {}", cli.config.display(), e))?;
+    let client = client(&config, cli.indexer.as_deref())?;
+
+    if let Some(function) = cli.command.search_function() {
+        return match cli.format {
+            Output::Xml => print_raw(&client.function(function, Format::Xml).await?),
+            Output::Json => print_json(&client.search(function).await?),
+            Output::Table => {
+                print_results(&client.search(function).await?);
+                Ok(())
+            }
+        };
+    }
+
+    match cli.command {
+        Command::Caps => match cli.format {
+            Output::Xml => print_raw(&client.function(Function::Caps, Format::Xml).await?),
+            Output::Json => print_json(&client.get_caps().await?),
+            Output::Table => {
+                print_caps(&client.get_caps().await?);
+                Ok(())
+            }
+        },
+        Command::Details { id } => match cli.format {
+            Output::Xml => print_raw(&client.function(Function::Details { id }, Format::Xml).await?),
+            format => {
+                let release = client.details(&id).await?.ok_or_else(|| format!("no release '{}'", id))?;
+                if format == Output::Json {
+                    print_json(&release)
+                } else {
+                    print_release(&release);
+                    Ok(())
+                }
+            }
+        },
+        Command::Get { id, output } => {
+            let nzb = client.get_nzb(&id).await?;
+            let output = output.unwrap_or_else(|| PathBuf::from(format!("{}.nzb", sanitize(&id))));
+            if output.as_os_str() == "-" {
+                print_raw(&nzb)
+            } else {
+                fs::write(&output, &nzb)?;
+                eprintln!("Saved {} bytes to {}", nzb.len(), output.display());
+                Ok(())
+            }
+        }
+        Command::User { username } => {
+            let xml = client.function(Function::User { username }, Format::Xml).await?;
+            match cli.format {
+                Output::Xml => print_raw(&xml),
+                format => {
+                    let user = serde_xml_rs::from_str::<BTreeMap<String, String>>(&xml)?;
+                    if format == Output::Json {
+                        print_json(&user)
+                    } else {
+                        print_table(&["Field", "Value"], user.into_iter().map(|(k, v)| vec![k, v]).collect());
+                        Ok(())
+                    }
+                }
+            }
+        }
+        Command::Search { .. } | Command::Tvsearch { .. } | Command::Movie { .. } => unreachable!(),
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+fn main() -> ExitCode {
+    exit(async_std::task::block_on(run(Cli::parse())))
+}
+
+#[cfg(feature = "sync")]
+fn main() -> ExitCode {
+    exit(run(Cli::parse()))
+}
+
+fn exit(result: CliResult<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn print_raw(data: &str) -> CliResult<()> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(data.as_bytes())?;
+    if !data.ends_with('\n') {
+        stdout.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+fn print_json(value: &impl serde::Serialize) -> CliResult<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn print_results(result: &ActiveSearchResult) {
+    let rows = result.items.iter()
+        .map(Release::from_item_ref)
+        .map(|release| vec![
+            release.title().unwrap_or_default().to_string(),
+            release.size().map(human_size).unwrap_or_default(),
+            release.categories().iter().map(u32::to_string).collect::<Vec<_>>().join(","),
+            release.pub_date().unwrap_or_default().to_string(),
+            release.guid().unwrap_or_default().to_string(),
+        ])
+        .collect();
+    print_table(&["Title", "Size", "Category", "Published", "Guid"], rows);
+    println!(
+        "{} items, offset {} of {}",
+        result.items.len(), result.search_offset.offset, result.search_offset.total.map_or("?".to_string(), |t| t.to_string()),
+    );
+}
+
+fn print_caps(caps: &Caps) {
+    println!("{} ({}), detected as {}", caps.server.title(), caps.server.url(), ServerSoftware::detect(caps));
+    println!("Limits: max {}, default {}", caps.limits.max(), caps.limits.default());
+    if caps.retention.days() > 0 {
+        println!("Retention: {} days", caps.retention.days());
+    }
+    println!();
+
+    let searching = &caps.searching;
+    let searches = [
+        ("search", searching.search()),
+        ("tv-search", searching.tv_search()),
+        ("movie-search", searching.movie_search()),
+        ("audio-search", searching.audio_search()),
+        ("book-search", searching.book_search()),
+    ];
+    let rows = searches.iter()
+        .filter_map(|(name, search)| Some((name, (*search)?)))
+        .map(|(name, search)| vec![
+            name.to_string(),
+            if search.is_available() { "yes" } else { "no" }.to_string(),
+            search.supported_params().join(","),
+        ])
+        .collect();
+    print_table(&["Function", "Available", "Parameters"], rows);
+    println!();
+
+    let mut rows = Vec::new();
+    for category in caps.categories.categories() {
+        rows.push(vec![category.id().to_string(), category.name().to_string()]);
+        for sub in category.sub_categories() {
+            rows.push(vec![sub.id().to_string(), format!("  {}", sub.name())]);
+        }
+    }
+    print_table(&["Category", "Name"], rows);
+}
+
+fn print_release(release: &Release) {
+    let mut rows = vec![
+        vec!["title".to_string(), release.title().unwrap_or_default().to_string()],
+        vec!["guid".to_string(), release.guid().unwrap_or_default().to_string()],
+        vec!["published".to_string(), release.pub_date().unwrap_or_default().to_string()],
+        vec!["download".to_string(), release.download_url().unwrap_or_default().to_string()],
+    ];
+    let attrs = release.attrs().into_iter().collect::<BTreeMap<_, _>>();
+    rows.extend(attrs.into_iter().map(|(name, value)| vec![name.clone(), value.clone()]));
+    print_table(&["Field", "Value"], rows);
+}
+
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths = headers.iter().map(|h| h.chars().count()).collect::<Vec<_>>();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: Vec<&str>| {
+        let cells = cells.iter().zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = *width))
+            .collect::<Vec<_>>();
+        println!("{}", cells.join("  ").trim_end());
+    };
+    line(headers.to_vec());
+    line(widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>().iter().map(String::as_str).collect());
+    for row in &rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
+
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Makes a guid usable as file name
+fn sanitize(id: &str) -> String {
+    id.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '.' || c == '_' { c } else { '_' }).collect()
+}
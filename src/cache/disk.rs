@@ -0,0 +1,162 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache::{CacheKey, ResponseCache};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    key: String,
+    expires: u64,
+    response: String,
+}
+
+/// A cache that stores one JSON file per response in a directory, so entries survive restarts.
+///
+/// I/O errors are logged and treated as cache misses.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl DiskCache {
+    /// Creates the cache, creating `dir` if it does not exist yet.
+    pub fn new(dir: impl AsRef<Path>, ttl: Duration) -> std::io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            ttl,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", fnv1a(key.as_str().as_bytes())))
+    }
+}
+
+impl ResponseCache for DiskCache {
+    fn get(&self, key: &CacheKey) -> Option<String> {
+        let path = self.path(key);
+        let data = fs::read(&path).ok()?;
+
+        match serde_json::from_slice::<Entry>(&data) {
+            Ok(entry) if entry.key == key.as_str() && entry.expires > now() => Some(entry.response),
+            Ok(entry) if entry.key != key.as_str() => None,
+            _ => {
+                self.remove(key);
+                None
+            }
+        }
+    }
+
+    fn insert(&self, key: CacheKey, response: String) {
+        let entry = Entry {
+            key: key.to_string(),
+            expires: now() + self.ttl.as_secs(),
+            response,
+        };
+
+        let res = serde_json::to_vec(&entry)
+            .map_err(std::io::Error::from)
+            .and_then(|data| fs::write(self.path(&key), data));
+        if let Err(e) = res {
+            log::warn!("Could not write cache entry to {:?}: {}", self.dir, e);
+        }
+    }
+
+    fn remove(&self, key: &CacheKey) {
+        let _ = fs::remove_file(self.path(key));
+    }
+
+    fn clear(&self) {
+        if let Ok(entries) = fs::read_dir(&self.dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    let _ = fs::remove_file(path);
+                }
+            }
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// FNV-1a, used for file names because it is stable across Rust versions.
+pub(crate) fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::time::Duration;
+
+    use crate::cache::{CacheKey, DiskCache, ResponseCache};
+    use crate::cache::disk::Entry;
+
+    fn key(q: &str) -> CacheKey {
+        CacheKey::new("http://idx/api", &BTreeMap::from([("q".to_string(), q.to_string())]))
+    }
+
+    fn cache(name: &str, ttl: Duration) -> DiskCache {
+        let dir = std::env::temp_dir().join(format!("newznab-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        DiskCache::new(dir, ttl).unwrap()
+    }
+
+    #[test]
+    fn round_trips() {
+        let cache = cache("round-trip", Duration::from_secs(60));
+        cache.insert(key("a"), "A".to_string());
+        assert_eq!(cache.get(&key("a")), Some("A".to_string()));
+        assert_eq!(cache.get(&key("b")), None);
+
+        let reopened = DiskCache::new(cache.dir(), Duration::from_secs(60)).unwrap();
+        assert_eq!(reopened.get(&key("a")), Some("A".to_string()));
+
+        cache.remove(&key("a"));
+        assert_eq!(cache.get(&key("a")), None);
+        cache.insert(key("b"), "B".to_string());
+        cache.clear();
+        assert_eq!(fs::read_dir(cache.dir()).unwrap().count(), 0);
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn entries_expire() {
+        let cache = cache("expiry", Duration::ZERO);
+        cache.insert(key("a"), "A".to_string());
+        assert_eq!(cache.get(&key("a")), None);
+        assert!(!cache.path(&key("a")).exists());
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn ignores_colliding_file_names() {
+        let cache = cache("collision", Duration::from_secs(60));
+        // another key that hashes to the same file name
+        let entry = Entry { key: key("b").to_string(), expires: u64::MAX, response: "B".to_string() };
+        fs::write(cache.path(&key("a")), serde_json::to_vec(&entry).unwrap()).unwrap();
+
+        assert_eq!(cache.get(&key("a")), None);
+        assert!(cache.path(&key("a")).exists());
+
+        fs::write(cache.path(&key("a")), "not json").unwrap();
+        assert_eq!(cache.get(&key("a")), None);
+        assert!(!cache.path(&key("a")).exists());
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+}
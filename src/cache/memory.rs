@@ -0,0 +1,149 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::cache::{CacheKey, ResponseCache};
+
+#[derive(Debug)]
+struct Entry {
+    response: String,
+    expires: Instant,
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    entries: HashMap<CacheKey, Entry>,
+    /// usage tick -> key, the smallest tick is the least recently used entry
+    recency: BTreeMap<u64, CacheKey>,
+    tick: u64,
+}
+
+impl State {
+    fn touch(&mut self, key: &CacheKey) {
+        self.tick += 1;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.used);
+            entry.used = self.tick;
+            self.recency.insert(self.tick, key.clone());
+        }
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.used);
+        }
+    }
+}
+
+/// An in-memory least-recently-used cache whose entries expire after a fixed time to live.
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    ttl: Duration,
+    state: Mutex<State>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            ttl,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        Self::new(256, Duration::from_secs(10 * 60))
+    }
+}
+
+impl ResponseCache for MemoryCache {
+    fn get(&self, key: &CacheKey) -> Option<String> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        match state.entries.get(key) {
+            Some(entry) if entry.expires > Instant::now() => {}
+            Some(_) => {
+                state.remove(key);
+                return None;
+            }
+            None => return None,
+        }
+
+        state.touch(key);
+        state.entries.get(key).map(|e| e.response.clone())
+    }
+
+    fn insert(&self, key: CacheKey, response: String) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.remove(&key);
+
+        while state.entries.len() >= self.capacity {
+            let oldest = state.recency.iter().next().map(|(_, k)| k.clone());
+            match oldest {
+                Some(k) => state.remove(&k),
+                None => break,
+            }
+        }
+
+        state.entries.insert(key.clone(), Entry {
+            response,
+            expires: Instant::now() + self.ttl,
+            used: 0,
+        });
+        state.touch(&key);
+    }
+
+    fn remove(&self, key: &CacheKey) {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).remove(key);
+    }
+
+    fn clear(&self) {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner()) = State::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use crate::cache::{CacheKey, MemoryCache, ResponseCache};
+
+    fn key(q: &str) -> CacheKey {
+        CacheKey::new("http://idx/api", &BTreeMap::from([("q".to_string(), q.to_string())]))
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = MemoryCache::new(2, Duration::from_secs(60));
+        cache.insert(key("a"), "A".to_string());
+        cache.insert(key("b"), "B".to_string());
+        assert_eq!(cache.get(&key("a")), Some("A".to_string()));
+
+        cache.insert(key("c"), "C".to_string());
+        assert_eq!(cache.get(&key("b")), None);
+        assert_eq!(cache.get(&key("a")), Some("A".to_string()));
+        assert_eq!(cache.get(&key("c")), Some("C".to_string()));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn entries_expire() {
+        let cache = MemoryCache::new(2, Duration::ZERO);
+        cache.insert(key("a"), "A".to_string());
+        assert_eq!(cache.get(&key("a")), None);
+        assert!(cache.is_empty());
+    }
+}
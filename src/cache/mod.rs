@@ -0,0 +1,121 @@
+mod memory;
+mod disk;
+
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Display};
+
+pub use self::{
+    disk::DiskCache,
+    memory::MemoryCache,
+};
+pub(crate) use disk::fnv1a;
+
+use crate::secret::is_secret_param;
+
+/// Identifies a cached response by the API url and the normalized request payload.
+///
+/// The payload is sorted and percent-encoded. The api key only goes in as a hash, so
+/// cache files never contain it while clients with different keys (which may see
+/// different results) keep separate entries.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    pub fn new(api_url: impl AsRef<str>, payload: &BTreeMap<String, String>) -> Self {
+        let normalized = payload.iter()
+            .map(|(k, v)| {
+                let k = k.to_lowercase();
+                // only a hash of secrets becomes part of the key
+                let v = if is_secret_param(&k) {
+                    format!("{:016x}", fnv1a(v.as_bytes()))
+                } else {
+                    encode(v)
+                };
+                (encode(&k), v)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let mut key = api_url.as_ref().trim_end_matches('/').to_string();
+        for (i, (k, v)) in normalized.iter().enumerate() {
+            key.push(if i == 0 { '?' } else { '&' });
+            key.push_str(k);
+            key.push('=');
+            key.push_str(v);
+        }
+        Self(key)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for CacheKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Percent-encodes everything but unreserved characters, so `&` and `=` in values can't
+/// make two payloads share a key.
+fn encode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => out.push(byte as char),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+/// A store for successful API responses, used by the [`Client`](crate::Client) to avoid
+/// re-issuing identical requests.
+///
+/// Implementations decide about expiry on their own, the client only ever inserts
+/// responses that did not contain a Newznab error.
+pub trait ResponseCache: Debug + Send + Sync {
+    fn get(&self, key: &CacheKey) -> Option<String>;
+    fn insert(&self, key: CacheKey, response: String);
+    fn remove(&self, key: &CacheKey);
+    fn clear(&self);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::cache::CacheKey;
+
+    #[test]
+    fn key_is_normalized() {
+        let a = BTreeMap::from([
+            ("t".to_string(), "search".to_string()),
+            ("q".to_string(), "foo".to_string()),
+            ("apikey".to_string(), "secret".to_string()),
+        ]);
+        let b = BTreeMap::from([
+            ("q".to_string(), "foo".to_string()),
+            ("T".to_string(), "search".to_string()),
+            ("apikey".to_string(), "secret".to_string()),
+        ]);
+
+        assert_eq!(CacheKey::new("http://idx/api/", &a), CacheKey::new("http://idx/api", &b));
+        assert!(CacheKey::new("http://idx/api", &a).as_str().starts_with("http://idx/api?apikey="));
+        assert!(CacheKey::new("http://idx/api", &a).as_str().ends_with("&q=foo&t=search"));
+        assert!(!CacheKey::new("http://idx/api", &a).as_str().contains("secret"));
+    }
+
+    #[test]
+    fn key_keeps_payloads_apart() {
+        let key = |pairs: &[(&str, &str)]| {
+            let payload = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+            CacheKey::new("http://idx/api", &payload)
+        };
+
+        assert_ne!(key(&[("q", "a&t=movie")]), key(&[("q", "a"), ("t", "movie")]));
+        assert_ne!(key(&[("q", "foo ")]), key(&[("q", "foo")]));
+        assert_ne!(key(&[("q", "foo"), ("apikey", "one")]), key(&[("q", "foo"), ("apikey", "two")]));
+        assert_eq!(key(&[("q", "a b&c")]).as_str(), "http://idx/api?q=a%20b%26c");
+    }
+}
@@ -1,22 +1,46 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+#[cfg(feature = "async")]
 use async_std::task;
 use bytes::Bytes;
 use maybe_async::maybe_async;
 
 use Format::Xml;
-use maybe_http_client::{HttpClient, HttpClientError};
+use maybe_http_client::HttpClient;
 
 use crate::common::{Format, Function};
 use crate::common::error::ModelError;
-use crate::common::models::{ActiveSearchResult, Caps, NewznabError, NewznabRawError, SearchResult};
+use crate::common::models::{ActiveSearchResult, Caps, ExtendedAttributes, NewznabError, NewznabRawError, PageInfo, Release, RssItem, SearchParameters, SearchResult};
 use crate::Error;
+use crate::cache::{CacheKey, ResponseCache};
+use crate::feed::FeedSource;
+use crate::fixture::{FixtureMode, RecordingTransport, ReplayTransport};
+use crate::health::HealthTracker;
+use crate::quirks::{Quirks, ServerSoftware};
+use crate::http::{HttpOptions, ReqwestTransport};
+use crate::rate_limit::RateLimiter;
+use crate::retry::{self, RetryPolicy};
+use crate::secret::{is_secret_param, ApiKey, REDACTED};
+use crate::transport::Transport;
 
 pub struct ClientBuilder {
     url: Option<String>,
     endpoint: String,
-    api_token: Option<String>,
+    api_token: Option<ApiKey>,
     caps: Caps,
+    retry: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    health: Option<HealthTracker>,
+    cache: Option<Arc<dyn ResponseCache>>,
+    extended_attributes: ExtendedAttributes,
+    categories: Vec<u32>,
+    server: Option<ServerSoftware>,
+    transport: Option<Arc<dyn Transport>>,
+    fixtures: Option<FixtureMode>,
+    http: HttpOptions,
 }
 
 impl Default for ClientBuilder {
@@ -26,6 +50,16 @@ impl Default for ClientBuilder {
             endpoint: "/api".to_string(),
             api_token: None,
             caps: Default::default(),
+            retry: RetryPolicy::none(),
+            rate_limiter: None,
+            health: None,
+            cache: None,
+            extended_attributes: ExtendedAttributes::Default,
+            categories: Vec::new(),
+            server: None,
+            transport: None,
+            fixtures: None,
+            http: HttpOptions::default(),
         }
     }
 }
@@ -39,30 +73,171 @@ impl ClientBuilder {
         self.url = Some(value.as_ref().to_string());
         self
     }
+    /// The path of the API below `url`, `/api` by default. Torznab indexers behind Jackett
+    /// or Prowlarr use paths like `/api/v2.0/indexers/<name>/results/torznab/api`.
     pub fn endpoint(mut self, value: impl AsRef<str>) -> Self {
         self.endpoint = value.as_ref().to_string();
         self
     }
-    pub fn api_token(mut self, value: impl AsRef<str>) -> Self {
-        self.api_token = Some(value.as_ref().to_string());
+    pub fn api_token(mut self, value: impl Into<ApiKey>) -> Self {
+        self.api_token = Some(value.into());
+        self
+    }
+    /// Sets the [`RetryPolicy`] applied to every API call, by default nothing is retried.
+    pub fn retry(mut self, value: RetryPolicy) -> Self {
+        self.retry = value;
+        self
+    }
+    /// Throttles every request, pass a clone of the same [`RateLimiter`] to several builders
+    /// to let them share one budget.
+    pub fn rate_limiter(mut self, value: impl Into<RateLimiter>) -> Self {
+        self.rate_limiter = Some(value.into());
+        self
+    }
+    /// Tracks the health of the indexer and stops sending requests while it keeps failing,
+    /// see [`HealthPolicy`](crate::health::HealthPolicy). Clients sharing a tracker share
+    /// its cooldown.
+    pub fn health(mut self, value: impl Into<HealthTracker>) -> Self {
+        self.health = Some(value.into());
+        self
+    }
+    /// Caches successful `caps` and `search` responses, the same cache may be shared
+    /// between several clients.
+    pub fn cache(mut self, value: Arc<dyn ResponseCache>) -> Self {
+        self.cache = Some(value);
+        self
+    }
+    /// The extended attributes every search requests, unless the search itself sets
+    /// `extended` or `attrs`.
+    pub fn extended_attributes(mut self, value: ExtendedAttributes) -> Self {
+        self.extended_attributes = value;
+        self
+    }
+    /// The categories every search is restricted to, unless the search itself sets `cat`.
+    pub fn categories(mut self, value: impl IntoIterator<Item = u32>) -> Self {
+        self.categories = value.into_iter().collect();
+        self
+    }
+    /// The indexer software, detected from the caps if not set. Its [`Quirks`] are applied
+    /// to every request and response.
+    pub fn server(mut self, value: ServerSoftware) -> Self {
+        self.server = Some(value);
+        self
+    }
+    /// Maximum time to establish a connection
+    pub fn connect_timeout(mut self, value: Duration) -> Self {
+        self.http.connect_timeout = Some(value);
+        self
+    }
+    /// Maximum time between two reads of a response
+    pub fn read_timeout(mut self, value: Duration) -> Self {
+        self.http.read_timeout = Some(value);
+        self
+    }
+    /// Maximum time of a whole request
+    pub fn timeout(mut self, value: Duration) -> Self {
+        self.http.timeout = Some(value);
+        self
+    }
+    /// Routes all requests through an `http://`, `https://` or (with the `socks` feature)
+    /// `socks5://` proxy, credentials can be part of the url.
+    pub fn proxy(mut self, value: impl AsRef<str>) -> Self {
+        self.http.proxy = Some(value.as_ref().to_string());
+        self
+    }
+    pub fn user_agent(mut self, value: impl AsRef<str>) -> Self {
+        self.http.user_agent = Some(value.as_ref().to_string());
+        self
+    }
+    /// Adds a header sent with every request
+    pub fn header(mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.http.headers.insert(name.as_ref().to_string(), value.as_ref().to_string());
+        self
+    }
+    /// Trusts an additional CA certificate in PEM format, requires the `rustls-tls` or
+    /// `native-tls` feature.
+    pub fn root_certificate(mut self, pem: impl AsRef<[u8]>) -> Self {
+        self.http.root_certificates.push(pem.as_ref().to_vec());
+        self
+    }
+    /// Accepts invalid and self-signed certificates, requires the `rustls-tls` or
+    /// `native-tls` feature. Only use this for indexers you host yourself.
+    pub fn danger_accept_invalid_certs(mut self, value: bool) -> Self {
+        self.http.accept_invalid_certs = value;
+        self
+    }
+    /// Sends all requests through `value` instead of the default [`HttpClient`], it can't
+    /// be combined with the HTTP options of this builder.
+    pub fn transport(mut self, value: Arc<dyn Transport>) -> Self {
+        self.transport = Some(value);
+        self
+    }
+    /// Records all traffic to fixture files or replays it from them instead of sending
+    /// requests, see [`FixtureMode`].
+    pub fn fixtures(mut self, value: FixtureMode) -> Self {
+        self.fixtures = Some(value);
         self
     }
 
     pub fn to_client(self) -> Client {
         self.into()
     }
+
+    /// Like [`to_client`](Self::to_client), but fails with [`Error::Config`] instead of
+    /// panicking if the builder is incomplete or contradicts itself, and right away for
+    /// invalid HTTP options.
+    pub fn try_build(mut self) -> Result<Client, Error> {
+        self.validate()?;
+        if self.transport.is_none() && !self.http.is_default() {
+            // invalid options fail here instead of on every request
+            self.transport = Some(Arc::new(ReqwestTransport::try_new(&self.http)?));
+            self.http = HttpOptions::default();
+        }
+        Ok(self.into())
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if self.url.is_none() {
+            return Err(Error::Config("an 'url' needs to be specified".to_string()));
+        }
+        if self.transport.is_some() && !self.http.is_default() {
+            return Err(Error::Config("HTTP options can't be combined with a custom transport".to_string()));
+        }
+        Ok(())
+    }
 }
 
 #[cfg_attr(target_arch = "wasm32", maybe_async(? Send))]
 #[cfg_attr(not(target_arch = "wasm32"), maybe_async(AFIT))]
 impl Into<Client> for ClientBuilder {
     fn into(self) -> Client {
+        if let Err(e) = self.validate() {
+            panic!("{}", e);
+        }
+        let transport: Arc<dyn Transport> = match self.transport {
+            Some(transport) => transport,
+            None if !self.http.is_default() => Arc::new(ReqwestTransport::new(&self.http)),
+            None => Arc::new(HttpClient::default()),
+        };
+        let transport: Arc<dyn Transport> = match self.fixtures {
+            Some(FixtureMode::Record(dir)) => Arc::new(RecordingTransport::new(transport, dir)),
+            Some(FixtureMode::Replay(dir)) => Arc::new(ReplayTransport::new(dir)),
+            None => transport,
+        };
+
         let mut c = Client {
-            url: self.url.expect("An 'url' needs to be specified"),
+            url: self.url.unwrap_or_default(),
             endpoint: self.endpoint,
             api_token: self.api_token,
-            http: HttpClient::default(),
+            transport,
             caps: Default::default(),
+            retry: self.retry,
+            rate_limiter: self.rate_limiter,
+            health: self.health,
+            cache: self.cache,
+            extended_attributes: self.extended_attributes,
+            categories: self.categories,
+            server: self.server.unwrap_or_default(),
         };
 
         #[cfg(feature = "async")]
@@ -77,6 +252,11 @@ impl Into<Client> for ClientBuilder {
             c.caps = caps;
         }
 
+        if self.server.is_none() {
+            c.server = ServerSoftware::detect(&c.caps);
+            log::debug!("Detected server software: {}", c.server);
+        }
+
         c
     }
 }
@@ -86,43 +266,127 @@ impl Into<Client> for ClientBuilder {
 pub struct Client {
     pub(crate) url: String,
     pub(crate) endpoint: String,
-    pub(crate) api_token: Option<String>,
-    pub(crate) http: HttpClient,
+    pub(crate) api_token: Option<ApiKey>,
+    pub(crate) transport: Arc<dyn Transport>,
     pub(crate) caps: Caps,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) rate_limiter: Option<RateLimiter>,
+    pub(crate) health: Option<HealthTracker>,
+    pub(crate) cache: Option<Arc<dyn ResponseCache>>,
+    pub(crate) extended_attributes: ExtendedAttributes,
+    pub(crate) categories: Vec<u32>,
+    pub(crate) server: ServerSoftware,
+}
+
+/// Per-request options for [`Client::function_with`].
+#[derive(Debug, Default, Clone)]
+pub struct RequestOptions {
+    /// Skips the cache lookup, the fresh response still replaces the cached one
+    pub bypass_cache: bool,
 }
 
+impl RequestOptions {
+    pub fn bypass_cache() -> Self {
+        Self { bypass_cache: true }
+    }
+}
+
+/// Joins `path` to `base` with exactly one slash in between.
+fn join_url(base: &str, path: &str) -> String {
+    let mut base = base.to_string();
+    if !base.ends_with('/') && !path.starts_with("/") {
+        base.push('/');
+    } else if base.ends_with('/') && path.starts_with("/") {
+        let mut chars = base.chars();
+        // chars.next();
+        chars.next_back();
+        base = chars.as_str().to_string()
+    }
+    base + path
+}
+
+/// A failed attempt of an API call together with the delay the server asked us to wait.
+struct FailedAttempt {
+    error: Error,
+    retry_after: Option<Duration>,
+}
+
+impl From<Error> for FailedAttempt {
+    fn from(error: Error) -> Self {
+        Self { error, retry_after: None }
+    }
+}
 
 // #[maybe_async::maybe_async(AFIT)]
 impl Client {
     pub fn get_api_url(&self) -> String {
-        let mut base = self.url.clone();
-        if !base.ends_with('/') && !self.endpoint.starts_with("/") {
-            base.push('/');
-        } else if base.ends_with('/') && self.endpoint.starts_with("/") {
-            let mut chars = base.chars();
-            // chars.next();
-            chars.next_back();
-            base = chars.as_str().to_string()
-        }
-        // (base + self.endpoint.as_ref()) + "?o=json"
-        base + self.endpoint.as_ref()
+        join_url(&self.url, &self.endpoint)
+    }
+
+    /// The url of the indexer's RSS feed endpoint (`/rss`).
+    pub fn get_rss_url(&self) -> String {
+        join_url(&self.url, "/rss")
+    }
+
+    pub fn get_transport(&self) -> &Arc<dyn Transport> {
+        &self.transport
+    }
+
+    /// The capabilities fetched when the client was built, empty if that request failed.
+    pub fn known_caps(&self) -> &Caps {
+        &self.caps
+    }
+
+    pub fn get_retry_policy(&self) -> &RetryPolicy {
+        &self.retry
     }
 
-    pub fn get_http(&self) -> &HttpClient {
-        &self.http
+    pub fn get_rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_ref()
     }
 
-    pub fn get_api_key(&self) -> Option<&String> {
+    pub fn get_health(&self) -> Option<&HealthTracker> {
+        self.health.as_ref()
+    }
+
+    pub fn get_cache(&self) -> Option<&Arc<dyn ResponseCache>> {
+        self.cache.as_ref()
+    }
+
+    pub fn get_api_key(&self) -> Option<&ApiKey> {
         self.api_token.as_ref()
     }
 
+    /// The parameters sent with every request, including the plain api key.
+    #[deprecated(note = "contains the plain api key, use `redacted_payload` for output")]
     pub fn get_default_payload(&self) -> HashMap<String, String> {
-        let mut payload = HashMap::new();
+        self.default_payload().into_iter().collect()
+    }
+
+    /// The parameters sent with every request, with the api key redacted.
+    pub fn redacted_payload(&self) -> BTreeMap<String, String> {
+        self.default_payload().into_iter()
+            .map(|(k, v)| if is_secret_param(&k) { (k, REDACTED.to_string()) } else { (k, v) })
+            .collect()
+    }
+
+    /// Like [`redacted_payload`](Self::redacted_payload) with the real api key.
+    fn default_payload(&self) -> BTreeMap<String, String> {
+        let mut payload = BTreeMap::new();
         // payload.insert("o", "json");
-        if self.api_token.is_some() {
-            payload.insert("apikey".to_string(), self.api_token.as_ref().unwrap().to_string().clone());
+        if let Some(key) = &self.api_token {
+            payload.insert("apikey".to_string(), key.expose().to_string());
+        }
+        payload
+    }
+
+    /// Removes the api key from `text`, e.g. an error message or a logged url.
+    pub fn scrub(&self, text: &str) -> String {
+        let text = crate::secret::scrub_query(text);
+        match &self.api_token {
+            Some(key) => key.scrub(&text),
+            None => text,
         }
-        payload.into()
     }
 
     /// Returns the absolute URL for an endpoint in the API.
@@ -137,7 +401,13 @@ impl Client {
 
     #[maybe_async::maybe_async(AFIT)]
     pub async fn function(&self, f: Function, o: Format) -> Result<String, Error> {
-        let mut payload = self.get_default_payload();
+        self.function_with(f, o, &RequestOptions::default()).await
+    }
+
+    #[maybe_async::maybe_async(AFIT)]
+    pub async fn function_with(&self, f: Function, o: Format, options: &RequestOptions) -> Result<String, Error> {
+        let cacheable = matches!(f, Function::Caps | Function::Details { .. }) || f.search_parameters().is_some();
+        let mut payload = self.default_payload();
 
         match o {
             Xml => { payload.insert("o".to_string(), "xml".to_string()); }
@@ -149,16 +419,19 @@ impl Client {
         match f {
             Function::Caps => {}
             Function::Register { .. } => {}
-            Function::Search(p) => {
+            Function::Details { id } | Function::Get { id } => {
+                payload.insert("id".to_string(), id);
+            }
+            Function::User { username } => {
+                payload.insert("username".to_string(), username);
+            }
+            Function::Search(p) | Function::TvSearch(p) | Function::Movie(p) => {
+                let limit = self.request_limit(&p);
+                payload.insert("limit".to_string(), limit.to_string());
                 payload.insert("q".to_string(), p.q);
 
-                if let Some(value) = p.limit {
-                    payload.insert("limit".to_string(), value.to_string());
-                } else {
-                    payload.insert("limit".to_string(), self.caps.limits.max().to_string());
-                }
                 if let Some(value) = p.offset {
-                    payload.insert("offset".to_string(), value.to_string());
+                    payload.insert("offset".to_string(), self.quirks().request_offset(value, limit).to_string());
                 }
 
                 if let Some(params) = p.params {
@@ -167,50 +440,145 @@ impl Client {
                     }
 
                 }
+                if !payload.contains_key("extended") && !payload.contains_key("attrs") {
+                    self.extended_attributes.apply(&mut payload);
+                }
+                if !payload.contains_key("cat") && !self.categories.is_empty() {
+                    let cats = self.categories.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",");
+                    payload.insert("cat".to_string(), cats);
+                }
+            }
+        }
+
+        self.execute(&self.get_api_url(), &payload, cacheable, options).await
+    }
+
+    /// Sends `payload` to `url`, going through the cache, the rate limiter and the retry policy.
+    #[maybe_async::maybe_async(AFIT)]
+    async fn execute(&self, url: &str, payload: &BTreeMap<String, String>, cacheable: bool, options: &RequestOptions) -> Result<String, Error> {
+        let what = payload.get("t").map_or(url, |t| t.as_str());
+
+        let cache = self.cache.as_ref().filter(|_| cacheable);
+        let cache_key = cache.map(|_| CacheKey::new(url, payload));
+        if let (Some(cache), Some(key)) = (cache, &cache_key) {
+            if !options.bypass_cache {
+                if let Some(data) = cache.get(key) {
+                    log::debug!("Cache hit for '{}'", what);
+                    return Ok(data);
+                }
             }
         }
 
-        let resp = self.http.get(
-            self.get_api_url().as_str(),
-            None,
-            &payload.iter().map(
-                |(k, v)| { (k.clone(), v.clone()) }
-            ).collect(),
-        ).await;
-
-        match resp {
-            Ok(data) => {
-                match serde_json::from_str::<NewznabRawError>(data.as_str()) {
-                    Ok(raw) => {
-                        let e: NewznabError = raw.into();
-                        // Err(err)
-                        log::error!("Error: {}", e);
-                        Err(Error::from(e))
+        let mut attempt = 1;
+        loop {
+            match self.send(url, payload).await {
+                Ok(data) => {
+                    if let (Some(cache), Some(key)) = (cache, cache_key) {
+                        cache.insert(key, data.clone());
                     }
-                    Err(e) => {
-                        // means we got no error
-                        // println!("json_error: {:?}", e);
-                        log::debug!("Request successfull");
-                        Ok(data)
+                    return Ok(data);
+                }
+                Err(failed) => {
+                    match self.retry.next_delay(attempt, &failed.error, failed.retry_after) {
+                        Some(delay) => {
+                            log::warn!(
+                                "Attempt {}/{} of '{}' failed: {}, retrying in {:?}",
+                                attempt, self.retry.get_max_attempts(), what, failed.error, delay
+                            );
+                            retry::sleep(delay).await;
+                            attempt += 1;
+                        }
+                        None => {
+                            if attempt > 1 {
+                                log::error!("Giving up on '{}' after {} attempts", what, attempt);
+                            }
+                            return Err(failed.error);
+                        }
                     }
                 }
             }
-            // Err(ReqwestError::Io(e)) => {
-            //     eprintln!("failed to decode response: {}", &e);
-            //     Err(Error::from(e))
-            // },
-            Err(HttpClientError::StatusCode(response)) => {
-                let code = response.status();
-                let msg = "";
-                Err(
-                    Error::HttpStatusCode(
-                        code.as_u16(),
-                        String::from_utf8_lossy(&*response.bytes().await.unwrap()).to_string(),
-                    )
-                )
+        }
+    }
+
+    /// Fetches the newest releases of `categories` (all if empty) from the indexer's feed.
+    ///
+    /// Feed requests are never answered from the cache.
+    #[maybe_async::maybe_async(AFIT)]
+    pub async fn feed(&self, source: FeedSource, categories: &[u32], limit: u32) -> Result<Vec<RssItem>, Error> {
+        let mut payload = self.default_payload();
+        let cats = categories.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",");
+        if !cats.is_empty() {
+            payload.insert("cat".to_string(), cats);
+        }
+
+        let url = match source {
+            FeedSource::Api => {
+                payload.insert("t".to_string(), "search".to_string());
+                payload.insert("q".to_string(), String::new());
+                payload.insert("o".to_string(), "xml".to_string());
+                payload.insert("limit".to_string(), limit.to_string());
+                self.get_api_url()
             }
-            _ => {
-                Err(Error::Http(Box::new(resp.unwrap_err())))
+            FeedSource::Rss => {
+                if let Some(key) = &self.api_token {
+                    payload.insert("r".to_string(), key.expose().to_string());
+                }
+                payload.insert("dl".to_string(), "1".to_string());
+                payload.insert("num".to_string(), limit.to_string());
+                self.get_rss_url()
+            }
+        };
+
+        let data = self.execute(&url, &payload, false, &RequestOptions::default()).await?;
+        Ok(rss::Channel::from_str(&data).map_err(ModelError::from)?.items)
+    }
+
+    /// Performs a single request without retrying.
+    #[maybe_async::maybe_async(AFIT)]
+    async fn send(&self, url: &str, payload: &BTreeMap<String, String>) -> Result<String, FailedAttempt> {
+        if let Some(remaining) = self.health.as_ref().and_then(HealthTracker::cooldown_remaining) {
+            return Err(Error::Unavailable(remaining).into());
+        }
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await?;
+        }
+
+        let started = Instant::now();
+        let result = self.request(url, payload).await;
+        if let Some(health) = &self.health {
+            match &result {
+                Ok(_) => health.record_success(started.elapsed()),
+                Err(failed) => health.record_failure(&failed.error, started.elapsed()),
+            }
+        }
+        result
+    }
+
+    #[maybe_async::maybe_async(AFIT)]
+    async fn request(&self, url: &str, payload: &BTreeMap<String, String>) -> Result<String, FailedAttempt> {
+        let resp = self.transport.get(url, payload).await?;
+
+        if !resp.is_success() {
+            let retry_after = resp.header("retry-after").and_then(retry::parse_retry_after);
+            return Err(
+                FailedAttempt {
+                    error: Error::HttpStatusCode(resp.status, self.scrub(&resp.body)),
+                    retry_after,
+                }
+            );
+        }
+
+        match NewznabRawError::parse(resp.body.as_str()) {
+            Some(raw) => {
+                let e: NewznabError = raw.scrubbed(|text| self.scrub(text)).into();
+                // Err(err)
+                log::error!("Error: {}", e);
+                Err(Error::from(e).into())
+            }
+            None => {
+                // means we got no error
+                log::debug!("Request successfull");
+                Ok(resp.body)
             }
         }
     }
@@ -226,27 +594,110 @@ impl Client {
             ).map_err(|e| { ModelError::from(e) })?
         )
     }
+
+    /// The release with the guid `id`, `None` if the indexer returned no item.
+    #[maybe_async::maybe_async]
+    pub async fn details(&self, id: impl AsRef<str>) -> Result<Option<Release>, Error> {
+        let data = self.function(Function::Details { id: id.as_ref().to_string() }, Xml).await?;
+        let mut channel = rss::Channel::from_str(&data).map_err(ModelError::from)?;
+        self.response_quirks(&channel).normalize(&mut channel.items);
+        Ok(channel.items.into_iter().next().map(Release::new))
+    }
+
+    /// Downloads the NZB of the release with the guid `id`.
+    #[maybe_async::maybe_async]
+    pub async fn get_nzb(&self, id: impl AsRef<str>) -> Result<String, Error> {
+        self.function(Function::Get { id: id.as_ref().to_string() }, Xml).await
+    }
+
+    pub fn get_extended_attributes(&self) -> &ExtendedAttributes {
+        &self.extended_attributes
+    }
+
+    pub fn get_categories(&self) -> &[u32] {
+        &self.categories
+    }
+
+    /// The detected or configured indexer software.
+    pub fn get_server(&self) -> ServerSoftware {
+        self.server
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.server.quirks()
+    }
+
+    /// The quirks of the server, detected from `channel` if the server is unknown.
+    fn response_quirks(&self, channel: &rss::Channel) -> Quirks {
+        match self.server {
+            ServerSoftware::Unknown => ServerSoftware::detect_response(channel).quirks(),
+            server => server.quirks(),
+        }
+    }
+
+    /// The number of items requested per page, the caps' maximum limited by the [`Quirks`].
+    pub fn page_size(&self) -> u32 {
+        self.quirks().page_size(self.caps.limits.max())
+    }
+
+    /// The `limit` sent for `params`, an explicit one is capped by the caps' maximum and the
+    /// [`Quirks`], as servers return short pages for larger limits.
+    pub fn request_limit(&self, params: &SearchParameters) -> u32 {
+        let Some(value) = params.limit else {
+            return self.page_size();
+        };
+        let caps_max = self.caps.limits.max();
+        let value = if caps_max > 0 { value.min(caps_max as u32) } else { value };
+        self.quirks().max_limit.map_or(value, |max| value.min(max))
+    }
+
+    /// The extended attributes `f` requests, the search's own setting wins over the client's.
+    pub fn requested_attributes(&self, f: &Function) -> ExtendedAttributes {
+        match f.search_parameters() {
+            Some(p) => p.params.as_ref()
+                .and_then(ExtendedAttributes::from_params)
+                .unwrap_or_else(|| self.extended_attributes.clone()),
+            None => ExtendedAttributes::Default,
+        }
+    }
+
     // async fn register(&self) -> Result<String, Self::Error>;
     //
     #[maybe_async::maybe_async]
     pub async fn search(&self, f: Function) -> Result<ActiveSearchResult, Error> {
-        let res = self.function(f.clone(), Xml).await;
-        if let Ok(xml_str) = res {
-            let sres = SearchResult::try_from(xml_str.as_str());
-            if let Ok(sr) = sres {
-                Ok(ActiveSearchResult {
-                    // client: &self,
-                    search_parameters: f.unwrap_search(),
-                    search_offset: sr.offset,
-                    // fetch_size: sr.items.len(),
-                    items: sr.items,
-                })
-                // Ok(sr)
-            } else {
-                Err(Error::from(sres.unwrap_err()))
-            }
-        } else {
-            Err(res.unwrap_err())
+        self.search_with(f, &RequestOptions::default()).await
+    }
+
+    #[maybe_async::maybe_async]
+    pub async fn search_with(&self, f: Function, options: &RequestOptions) -> Result<ActiveSearchResult, Error> {
+        let Some(kind) = f.search_kind() else {
+            return Err(Error::NotASearch(f.to_string()));
+        };
+        let attributes = self.requested_attributes(&f);
+        let xml_str = self.function_with(f.clone(), Xml, options).await?;
+        let channel = rss::Channel::from_str(&xml_str).map_err(ModelError::from)?;
+        let quirks = self.response_quirks(&channel);
+        let mut sr = SearchResult::try_from(channel)?;
+        quirks.normalize(&mut sr.items);
+        attributes.retain(&mut sr.items);
+        let search_parameters = f.unwrap_search();
+        let limit = self.request_limit(&search_parameters);
+        let requested = self.quirks().page_start(search_parameters.offset.unwrap_or(0), limit);
+        if sr.offset.total.is_none() || self.quirks().offset_in_pages {
+            // without newznab:response the page starts where it was requested, servers
+            // counting pages may report the page number
+            sr.offset.offset = requested;
         }
+        let page = PageInfo::new(requested, limit, &sr);
+        Ok(ActiveSearchResult::new(
+            self.get_api_url(),
+            kind,
+            search_parameters,
+            attributes,
+            page,
+            sr,
+            quirks.exact_total,
+        ))
     }
+
 }
\ No newline at end of file
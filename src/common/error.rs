@@ -18,4 +18,7 @@ pub enum ModelError {
 
     #[error("XmlParseError: {0}")]
     RssExtensionError(String),
+
+    #[error("InvalidSearchOffset: attribute '{name}' of newznab:response is not a number: {value:?}")]
+    InvalidSearchOffset { name: String, value: String },
 }
\ No newline at end of file
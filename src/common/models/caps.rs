@@ -1,11 +1,16 @@
 use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::common::Function;
 
 
-#[derive(Debug, Default, Deserialize, PartialEq, Clone)]
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
 pub struct Caps {
     pub server: Server,
+    /// Zero if the server sent no `<limits>`, see [`Client::page_size`](crate::Client::page_size)
+    #[serde(default)]
     pub limits: Limits,
+    #[serde(default)]
     pub retention: Retention,
     pub searching: Searching,
 
@@ -16,7 +21,8 @@ pub struct Caps {
     // categories: Categories,
 }
 
-#[derive(Debug, Default, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(default)]
 pub struct Server {
     title: String,
     email: String,
@@ -24,7 +30,14 @@ pub struct Server {
     image: String,
 }
 
-#[derive(Debug, Default, Deserialize, PartialEq, Clone)]
+impl Server {
+    pub fn title(&self) -> &str {&self.title}
+    pub fn email(&self) -> &str {&self.email}
+    pub fn url(&self) -> &str {&self.url}
+    pub fn image(&self) -> &str {&self.image}
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
 pub struct Limits {
     pub(crate) max: i32,
     pub(crate) default: i32,
@@ -35,23 +48,50 @@ impl Limits {
     pub fn default(&self) -> i32 {self.default}
 }
 
-#[derive(Debug, Default, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
 pub struct Retention {
     days: u32,
 }
+
+impl Retention {
+    pub fn days(&self) -> u32 {self.days}
+}
 // error: unknown rename rule `rename_all = "$value"`, expected one of "lowercase", "UPPERCASE", "PascalCase", "camelCase", "snake_case", "SCREAMING_SNAKE_CASE", "kebab-case", "SCREAMING-KEBAB-CASE"
 
-#[derive(Debug, Default, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct Searching {
     search: Option<Search>,
     tv_search: Option<Search>,
     movie_search: Option<Search>,
     audio_search: Option<Search>,
+    /// Torznab's name for `audio-search`
+    music_search: Option<Search>,
     book_search: Option<Search>,
 }
 
-#[derive(Debug, Default, Deserialize, PartialEq, Clone)]
+impl Searching {
+    pub fn search(&self) -> Option<&Search> {self.search.as_ref()}
+    pub fn tv_search(&self) -> Option<&Search> {self.tv_search.as_ref()}
+    pub fn movie_search(&self) -> Option<&Search> {self.movie_search.as_ref()}
+    /// The `audio-search`, or the `music-search` of Torznab indexers
+    pub fn audio_search(&self) -> Option<&Search> {self.audio_search.as_ref().or(self.music_search.as_ref())}
+    pub fn music_search(&self) -> Option<&Search> {self.music_search.as_ref()}
+    pub fn book_search(&self) -> Option<&Search> {self.book_search.as_ref()}
+
+    /// The capabilities for the search type of a [`Function`], `None` if the function is not
+    /// a search or the server did not advertise it.
+    pub fn for_function(&self, function: &Function) -> Option<&Search> {
+        match function {
+            Function::Search(_) => self.search(),
+            Function::TvSearch(_) => self.tv_search(),
+            Function::Movie(_) => self.movie_search(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
 pub struct Search {
     #[serde(deserialize_with = "bool_from_yes_no")]
     available: bool,
@@ -59,13 +99,27 @@ pub struct Search {
     supported_params: Option<String>,
 }
 
-#[derive(Debug, Default, Deserialize, PartialEq, Clone)]
+impl Search {
+    pub fn is_available(&self) -> bool {self.available}
+
+    pub fn supported_params(&self) -> Vec<&str> {
+        self.supported_params.as_deref()
+            .map(|p| p.split(',').map(str::trim).filter(|p| !p.is_empty()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn supports_param(&self, name: &str) -> bool {
+        self.supported_params().contains(&name)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
 pub struct Categories {
-    #[serde(rename = "category")]
+    #[serde(rename = "category", default)]
     categories: Vec<Category>,
 }
 
-#[derive(Debug, Default, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
 pub struct Category {
     id: String,
     name: String,
@@ -73,12 +127,40 @@ pub struct Category {
     sub_categories: Option<Vec<SubCategory>>,
 }
 
-#[derive(Debug, Default, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
 pub struct SubCategory {
     id: String,
     name: String,
 }
 
+impl Categories {
+    pub fn categories(&self) -> &[Category] {&self.categories}
+}
+
+impl Category {
+    pub fn id(&self) -> &str {&self.id}
+    pub fn name(&self) -> &str {&self.name}
+    pub fn sub_categories(&self) -> &[SubCategory] {self.sub_categories.as_deref().unwrap_or_default()}
+}
+
+impl SubCategory {
+    pub fn id(&self) -> &str {&self.id}
+    pub fn name(&self) -> &str {&self.name}
+}
+
+impl Caps {
+    /// Returns `false` only if the server explicitly reported the search type of `function`
+    /// as unavailable, unknown capabilities are assumed to be supported.
+    pub fn supports(&self, function: &Function) -> bool {
+        match function {
+            Function::Search(_) | Function::TvSearch(_) | Function::Movie(_) => {
+                self.searching.for_function(function).is_none_or(Search::is_available)
+            }
+            _ => true,
+        }
+    }
+}
+
 impl TryFrom<String> for Caps {
     type Error = serde_xml_rs::Error;
 
@@ -89,16 +171,22 @@ impl TryFrom<String> for Caps {
 }
 
 /// Helper function for serde
-/// Deserializes a yes/no string field into a bool
+/// Deserializes a yes/no string field (or a bool, as serialized to JSON) into a bool
 fn bool_from_yes_no<'de, D>(deserializer: D) -> Result<bool, D::Error>
     where
         D: Deserializer<'de>,
 {
-    use serde::de::Error;
-    use serde::Deserialize;
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum YesNo {
+        Bool(bool),
+        String(String),
+    }
 
-    String::deserialize(deserializer)
-        .and_then(|string| if string == "yes" {Ok(true)} else {Ok(false)} )
+    match YesNo::deserialize(deserializer)? {
+        YesNo::Bool(value) => Ok(value),
+        YesNo::String(string) => Ok(string == "yes" || string == "true"),
+    }
 }
 
 #[cfg(test)]
@@ -148,6 +236,7 @@ mod tests {
                 tv_search: Search { available: true, supported_params: Some("q,season,ep,cat,limit,offset,minage,maxage,minsize,maxsize,rid,tvdbid,tvmazeid,imdbid,traktid".to_string()) }.into(),
                 movie_search: Search { available: true, supported_params: Some("q,cat,limit,offset,minage,maxage,minsize,maxsize,imdbid,tmdbid".to_string()) }.into(),
                 audio_search: Search { available: false, supported_params: Some("".to_string()) }.into(),
+                music_search: None,
                 book_search: Search { available: true, supported_params: Some("q,author,title,cat,limit,offset,minage,maxage,minsize,maxsize".to_string()) }.into(),
             },
             categories: Categories {
@@ -169,8 +258,43 @@ mod tests {
             },
         };
 
-        let deserialized = serde_xml_rs::from_str::<Caps>(&input).unwrap();
+        let deserialized = serde_xml_rs::from_str::<Caps>(input).unwrap();
 
         assert_eq!(deserialized, expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn deserialize_torznab_caps() {
+        let input = r#"
+        <caps>
+            <server title="Jackett"/>
+            <limits default="100" max="100"/>
+            <searching>
+                <search available="yes" supportedParams="q"/>
+                <tv-search available="yes" supportedParams="q,season,ep,imdbid"/>
+                <movie-search available="no" supportedParams="q"/>
+                <music-search available="yes" supportedParams="q,album,artist"/>
+            </searching>
+            <categories>
+                <category id="5000" name="TV">
+                    <subcat id="5040" name="TV/HD"/>
+                </category>
+            </categories>
+        </caps>
+        "#;
+
+        let caps = serde_xml_rs::from_str::<Caps>(input).unwrap();
+
+        assert_eq!(caps.server.title, "Jackett");
+        assert_eq!(caps.retention, Retention::default());
+        assert!(caps.searching.tv_search().unwrap().supports_param("imdbid"));
+        assert!(!caps.searching.movie_search().unwrap().is_available());
+        assert!(caps.searching.book_search().is_none());
+        assert!(caps.searching.music_search().unwrap().supports_param("artist"));
+        assert_eq!(caps.searching.audio_search(), caps.searching.music_search());
+
+        let json = serde_json::to_string(&caps).unwrap();
+        assert!(json.contains(r#""tv-search":{"available":true,"supportedParams":"q,season,ep,imdbid"}"#));
+        assert_eq!(serde_json::from_str::<Caps>(&json).unwrap(), caps);
+    }
+}
@@ -1,9 +1,9 @@
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize};
 use thiserror::Error;
 
 /// A raw Error that is returned from a Newznab API calls
 /// This is just a step between to simplify the Deserialization
-#[derive(Debug, Error, Deserialize)]
+#[derive(Debug, Clone, Error, Serialize, Deserialize)]
 #[error("{code}: {description}")]
 pub struct NewznabRawError {
     search_type: Option<String>,
@@ -14,7 +14,7 @@ pub struct NewznabRawError {
 
 /// An Error that is returned from a Newznab API call
 /// 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error, Serialize, Deserialize)]
 pub enum NewznabError {
 
     /// 100
@@ -98,6 +98,32 @@ pub enum NewznabError {
     UnknownOtherError { code: u16, message: String, inner: NewznabRawError }, // 900-999
 }
 
+impl NewznabRawError {
+    /// Applies `scrub` to the description, indexers may echo the request in it.
+    pub(crate) fn scrubbed(mut self, scrub: impl Fn(&str) -> String) -> Self {
+        self.description = scrub(&self.description);
+        self
+    }
+
+    /// Parses an error response in either JSON or XML (`<error code=".." description=".."/>`)
+    /// format, Torznab indexers answer in XML even if JSON was requested.
+    pub(crate) fn parse(data: &str) -> Option<Self> {
+        if let Ok(raw) = serde_json::from_str::<Self>(data) {
+            return Some(raw);
+        }
+
+        let trimmed = data.trim_start();
+        let xml = trimmed.strip_prefix("<?xml").map_or(trimmed, |rest| {
+            rest.split_once("?>").map_or("", |(_, body)| body.trim_start())
+        });
+        if xml.starts_with("<error") {
+            serde_xml_rs::from_str::<Self>(xml).ok()
+        } else {
+            None
+        }
+    }
+}
+
 impl From<NewznabRawError> for NewznabError {
     fn from(raw: NewznabRawError) -> Self {
         match raw.code {
@@ -135,8 +161,66 @@ fn u16_from_string<'de, D>(deserializer: D) -> Result<u16, D::Error>
         D: Deserializer<'de>,
 {
     use serde::de::Error;
-    use serde::Deserialize;
 
-    String::deserialize(deserializer)
-        .and_then(|string| string.parse::<u16>().map_err(|err| Error::custom(err.to_string())))
-}
\ No newline at end of file
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Code {
+        Number(u16),
+        String(String),
+    }
+
+    match Code::deserialize(deserializer)? {
+        Code::Number(code) => Ok(code),
+        Code::String(string) => string.trim().parse::<u16>().map_err(|err| Error::custom(err.to_string())),
+    }
+}
+
+impl NewznabError {
+    /// The numeric Newznab error code
+    pub fn code(&self) -> u16 {
+        match self {
+            NewznabError::IncorrectUserCredentials { code, .. }
+            | NewznabError::AccountSuspended { code, .. }
+            | NewznabError::InsufficientPrivileges { code, .. }
+            | NewznabError::RegistrationDenied { code, .. }
+            | NewznabError::RegistrationsClosed { code, .. }
+            | NewznabError::RegistrationFailedEmailTaken { code, .. }
+            | NewznabError::RegistrationFailedEmailBadFormat { code, .. }
+            | NewznabError::RegistrationFailedDataError { code, .. }
+            | NewznabError::UnknownAccountError { code, .. }
+            | NewznabError::MissingParameter { code, .. }
+            | NewznabError::IncorrectParameter { code, .. }
+            | NewznabError::NoSuchFunction { code, .. }
+            | NewznabError::FunctionNotAvailable { code, .. }
+            | NewznabError::UnknownApiCallError { code, .. }
+            | NewznabError::NoSuchItem { code, .. }
+            | NewznabError::ItemAlreadyExists { code, .. }
+            | NewznabError::UnknownContentError { code, .. }
+            | NewznabError::UnknownError { code, .. }
+            | NewznabError::ApiDisabled { code, .. }
+            | NewznabError::UnknownOtherError { code, .. } => *code,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::models::{NewznabError, NewznabRawError};
+
+    #[test]
+    fn parse_json_and_xml_errors() {
+        let json = NewznabRawError::parse(r#"{"code": "100", "description": "Incorrect user credentials"}"#).unwrap();
+        assert!(matches!(NewznabError::from(json), NewznabError::IncorrectUserCredentials { code: 100, .. }));
+
+        let xml = NewznabRawError::parse(r#"<?xml version="1.0" encoding="UTF-8"?>
+            <error code="201" description="Incorrect parameter"/>"#).unwrap();
+        assert_eq!(NewznabError::from(xml).code(), 201);
+
+        assert!(NewznabRawError::parse("<caps></caps>").is_none());
+
+        let error = NewznabError::from(NewznabRawError::parse(r#"<error code="429" description="Request limit reached"/>"#).unwrap());
+        let json = serde_json::to_string(&error).unwrap();
+        let back = serde_json::from_str::<NewznabError>(&json).unwrap();
+        assert_eq!((back.code(), back.to_string()), (error.code(), error.to_string()));
+    }
+}
@@ -2,10 +2,11 @@ mod error;
 mod caps;
 mod search_result;
 mod search;
+mod release;
 
 use std::collections::HashMap;
 use std::fmt::Display;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 pub use error::{
     NewznabError,
@@ -16,6 +17,7 @@ pub use self::{
     caps::*,
     search::*,
     search_result::*,
+    release::*,
 };
 
 pub type RssItem = rss::Item;
@@ -32,7 +34,15 @@ pub enum Format {
 pub enum Function {
     Caps,
     Register{ email: String },
-    Search (SearchParameters)
+    Search (SearchParameters),
+    TvSearch (SearchParameters),
+    Movie (SearchParameters),
+    /// Details of a single release by its guid
+    Details{ id: String },
+    /// Downloads the NZB of a release by its guid
+    Get{ id: String },
+    /// Account information, e.g. the api and grab limits
+    User{ username: String },
 }
 
 impl Display for Function {
@@ -42,6 +52,11 @@ impl Display for Function {
                 Function::Caps => {"caps"}
                 Function::Register{..} => {"register"}
                 Function::Search { .. } => { "search" }
+                Function::TvSearch { .. } => { "tvsearch" }
+                Function::Movie { .. } => { "movie" }
+                Function::Details { .. } => { "details" }
+                Function::Get { .. } => { "get" }
+                Function::User { .. } => { "user" }
             }
         };
         write!(f, "{}", repr)
@@ -50,9 +65,59 @@ impl Display for Function {
 
 impl Function {
     pub fn unwrap_search(&self) -> SearchParameters {
+        match self.search_parameters() {
+            Some(params) => params.clone(),
+            None => {panic!("Called unwrap_search on a non Search Function")}
+        }
+    }
+
+    /// The parameters of `search`, `tvsearch` and `movie`, `None` for other functions.
+    pub fn search_parameters(&self) -> Option<&SearchParameters> {
+        match self {
+            Self::Search(params) | Self::TvSearch(params) | Self::Movie(params) => Some(params),
+            _ => None,
+        }
+    }
+
+    pub fn search_parameters_mut(&mut self) -> Option<&mut SearchParameters> {
+        match self {
+            Self::Search(params) | Self::TvSearch(params) | Self::Movie(params) => Some(params),
+            _ => None,
+        }
+    }
+
+    /// The same kind of search with other parameters, `None` if `self` is not a search.
+    pub fn with_search_parameters(&self, params: SearchParameters) -> Option<Function> {
+        Some(self.search_kind()?.function(params))
+    }
+
+    /// `None` for functions that are not searches.
+    pub fn search_kind(&self) -> Option<SearchKind> {
+        match self {
+            Self::Search(_) => Some(SearchKind::Search),
+            Self::TvSearch(_) => Some(SearchKind::TvSearch),
+            Self::Movie(_) => Some(SearchKind::Movie),
+            _ => None,
+        }
+    }
+}
+
+/// The search functions, without their parameters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchKind {
+    #[default]
+    Search,
+    TvSearch,
+    Movie,
+}
+
+impl SearchKind {
+    pub fn function(self, params: SearchParameters) -> Function {
         match self {
-            Self::Search(params) => params.clone(),
-            _ => {panic!("Called unwrap_search on a non Search Function")}
+            SearchKind::Search => Function::Search(params),
+            SearchKind::TvSearch => Function::TvSearch(params),
+            SearchKind::Movie => Function::Movie(params),
         }
     }
 }
@@ -0,0 +1,425 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use rss::{EnclosureBuilder, GuidBuilder, ItemBuilder};
+use rss::extension::{Extension, ExtensionBuilder};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::common::models::{GetNewznabExtension, RssItem, NAMESPACES};
+use crate::release_name::ReleaseName;
+
+/// A typed view on a release returned by a search, wrapping the underlying [`RssItem`].
+///
+/// `Release<&RssItem>` (see [`from_item_ref`](Release::from_item_ref)) views an item
+/// without cloning it. Serializes as [`ReleaseItem`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release<I = RssItem> {
+    item: I,
+}
+
+impl Release {
+    pub fn new(item: RssItem) -> Self {
+        Self { item }
+    }
+
+    pub fn into_item(self) -> RssItem {
+        self.item
+    }
+}
+
+impl<'a> Release<&'a RssItem> {
+    /// Views a borrowed [`RssItem`] as release without cloning it.
+    pub fn from_item_ref(item: &'a RssItem) -> Self {
+        Self { item }
+    }
+}
+
+impl<I: Borrow<RssItem>> Release<I> {
+    pub fn item(&self) -> &RssItem {
+        self.item.borrow()
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.item().title()
+    }
+
+    pub fn guid(&self) -> Option<&str> {
+        self.item().guid().map(|g| g.value())
+    }
+
+    pub fn link(&self) -> Option<&str> {
+        self.item().link()
+    }
+
+    pub fn pub_date(&self) -> Option<&str> {
+        self.item().pub_date()
+    }
+
+    /// The parsed `pubDate`
+    pub fn published(&self) -> Option<DateTime<FixedOffset>> {
+        DateTime::parse_from_rfc2822(self.pub_date()?.trim()).ok()
+    }
+
+    /// The title parsed as scene-style release name
+    pub fn parsed_name(&self) -> Option<ReleaseName> {
+        self.title().map(ReleaseName::parse)
+    }
+
+    /// The title lowercased and reduced to alphanumeric words, so that `Show.Name.S01E01` and
+    /// `show name s01e01` compare equal.
+    pub fn normalized_title(&self) -> Option<String> {
+        self.title().map(normalize_title)
+    }
+
+    /// All newznab attributes of this release
+    pub fn attrs(&self) -> HashMap<&String, &String> {
+        self.item().get_nn_ext().unwrap_or_default()
+    }
+
+    /// The value of a single newznab attribute, the first one if it is present multiple times
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attr_values(name).into_iter().next()
+    }
+
+    /// All values of an attribute that may be present multiple times, e.g. `category`
+    pub fn attr_values(&self, name: &str) -> Vec<&str> {
+        self.attr_elements()
+            .filter(|a| a.attrs.get("name").is_some_and(|n| n == name))
+            .filter_map(|a| a.attrs.get("value").map(|v| v.as_str()))
+            .collect()
+    }
+
+    /// The `attr` elements of both the newznab and the torznab namespace
+    fn attr_elements(&self) -> impl Iterator<Item = &Extension> {
+        NAMESPACES.iter()
+            .filter_map(|ns| self.item().extensions().get(*ns))
+            .filter_map(|namespace| namespace.get("attr"))
+            .flatten()
+    }
+
+    /// The size in bytes, taken from the `size` attribute or the enclosure length
+    pub fn size(&self) -> Option<u64> {
+        self.attr("size")
+            .and_then(|s| s.parse().ok())
+            .or_else(|| self.item().enclosure().and_then(|e| e.length().parse().ok()))
+            .filter(|s| *s > 0)
+    }
+
+    /// When the release was posted to usenet, taken from the `usenetdate` attribute or `pubDate`
+    pub fn posted(&self) -> Option<DateTime<FixedOffset>> {
+        self.attr("usenetdate")
+            .and_then(|d| DateTime::parse_from_rfc2822(d.trim()).ok())
+            .or_else(|| self.published())
+    }
+
+    /// Time since the release was [`posted`](Self::posted)
+    pub fn age(&self, now: DateTime<Utc>) -> Option<Duration> {
+        (now - self.posted()?.with_timezone(&Utc)).to_std().ok()
+    }
+
+    /// How often the release was downloaded, only returned by extended attribute requests
+    pub fn grabs(&self) -> Option<u32> {
+        self.attr("grabs").and_then(|s| s.parse().ok())
+    }
+
+    /// Returns `Some(true)` if the release is (or may contain) a password protected archive
+    pub fn is_passworded(&self) -> Option<bool> {
+        self.attr("password").map(|p| p.trim() != "0")
+    }
+
+    pub fn poster(&self) -> Option<&str> {
+        self.attr("poster")
+    }
+
+    /// The newznab category ids of this release
+    pub fn categories(&self) -> Vec<u32> {
+        self.attr_values("category")
+            .into_iter()
+            .filter_map(|c| c.parse().ok())
+            .collect()
+    }
+
+    /// The url of the nzb or torrent file
+    pub fn download_url(&self) -> Option<&str> {
+        self.item().enclosure().map(|e| e.url()).or(self.link())
+    }
+
+    /// Returns `true` for releases of a Torznab indexer.
+    pub fn is_torrent(&self) -> bool {
+        self.item().extensions().contains_key("torznab")
+            || self.infohash().is_some()
+            || self.magnet_url().is_some()
+    }
+
+    pub fn seeders(&self) -> Option<u32> {
+        self.attr("seeders").and_then(|s| s.parse().ok())
+    }
+
+    /// Seeders and leechers
+    pub fn peers(&self) -> Option<u32> {
+        self.attr("peers").and_then(|s| s.parse().ok())
+    }
+
+    /// Peers that are not seeding, derived from `peers` and `seeders`
+    pub fn leechers(&self) -> Option<u32> {
+        Some(self.peers()?.saturating_sub(self.seeders().unwrap_or(0)))
+    }
+
+    pub fn infohash(&self) -> Option<&str> {
+        self.attr("infohash")
+    }
+
+    /// The magnet link from the `magneturl` attribute, or the link or enclosure if they
+    /// are magnet uris
+    pub fn magnet_url(&self) -> Option<&str> {
+        self.attr("magneturl")
+            .or_else(|| self.link().filter(|l| l.starts_with("magnet:")))
+            .or_else(|| self.item().enclosure().map(|e| e.url()).filter(|u| u.starts_with("magnet:")))
+    }
+
+    /// Factor of the size counted as downloaded by the tracker, `0.0` means freeleech
+    pub fn download_volume_factor(&self) -> Option<f64> {
+        self.attr("downloadvolumefactor").and_then(|s| s.parse().ok())
+    }
+
+    /// Factor of the size counted as uploaded by the tracker
+    pub fn upload_volume_factor(&self) -> Option<f64> {
+        self.attr("uploadvolumefactor").and_then(|s| s.parse().ok())
+    }
+
+    pub fn minimum_ratio(&self) -> Option<f64> {
+        self.attr("minimumratio").and_then(|s| s.parse().ok())
+    }
+
+    /// Minimum seeding time in seconds
+    pub fn minimum_seed_time(&self) -> Option<u64> {
+        self.attr("minimumseedtime").and_then(|s| s.parse().ok())
+    }
+}
+
+/// Lowercases `title` and joins its alphanumeric words with single spaces.
+pub fn normalize_title(title: &str) -> String {
+    title.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl AsRef<Release> for Release {
+    fn as_ref(&self) -> &Release {
+        self
+    }
+}
+
+impl From<RssItem> for Release {
+    fn from(item: RssItem) -> Self {
+        Self::new(item)
+    }
+}
+
+impl From<Release> for RssItem {
+    fn from(release: Release) -> Self {
+        release.item
+    }
+}
+
+impl Serialize for Release {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ReleaseItem::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Release {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ReleaseItem::deserialize(deserializer).map(Release::from)
+    }
+}
+
+/// The stable serialized form of a [`Release`].
+///
+/// `size`, `categories` and `download_url` are derived from the other fields for
+/// convenience and ignored when converting back, `attrs` is the source of truth.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReleaseItem {
+    pub title: Option<String>,
+    pub guid: Option<String>,
+    pub link: Option<String>,
+    pub comments: Option<String>,
+    pub pub_date: Option<String>,
+    pub description: Option<String>,
+    pub enclosure: Option<ReleaseEnclosure>,
+    /// `newznab` or `torznab`
+    pub namespace: String,
+    /// All `attr` elements in document order
+    pub attrs: Vec<ReleaseAttr>,
+    pub size: Option<u64>,
+    pub categories: Vec<u32>,
+    pub download_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseEnclosure {
+    pub url: String,
+    pub length: String,
+    pub mime_type: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseAttr {
+    pub name: String,
+    pub value: String,
+}
+
+impl<I: Borrow<RssItem>> From<&Release<I>> for ReleaseItem {
+    fn from(release: &Release<I>) -> Self {
+        let item = release.item();
+        let namespace = NAMESPACES.iter()
+            .find(|ns| item.extensions().contains_key(**ns))
+            .unwrap_or(&NAMESPACES[0]);
+
+        Self {
+            title: item.title().map(str::to_string),
+            guid: release.guid().map(str::to_string),
+            link: item.link().map(str::to_string),
+            comments: item.comments().map(str::to_string),
+            pub_date: item.pub_date().map(str::to_string),
+            description: item.description().map(str::to_string),
+            enclosure: item.enclosure().map(|e| ReleaseEnclosure {
+                url: e.url().to_string(),
+                length: e.length().to_string(),
+                mime_type: e.mime_type().to_string(),
+            }),
+            namespace: namespace.to_string(),
+            attrs: release.attr_elements()
+                .filter_map(|a| Some(ReleaseAttr {
+                    name: a.attrs.get("name")?.clone(),
+                    value: a.attrs.get("value").cloned().unwrap_or_default(),
+                }))
+                .collect(),
+            size: release.size(),
+            categories: release.categories(),
+            download_url: release.download_url().map(str::to_string),
+        }
+    }
+}
+
+impl From<Release> for ReleaseItem {
+    fn from(release: Release) -> Self {
+        ReleaseItem::from(&release)
+    }
+}
+
+impl From<ReleaseItem> for Release {
+    fn from(value: ReleaseItem) -> Self {
+        let namespace = if NAMESPACES.contains(&value.namespace.as_str()) { value.namespace } else { NAMESPACES[0].to_string() };
+        let attrs = value.attrs.into_iter()
+            .map(|a| {
+                ExtensionBuilder::default()
+                    .name(format!("{}:attr", namespace))
+                    .attrs(BTreeMap::from([("name".to_string(), a.name), ("value".to_string(), a.value)]))
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let extensions = if attrs.is_empty() {
+            BTreeMap::new()
+        } else {
+            BTreeMap::from([(namespace, BTreeMap::from([("attr".to_string(), attrs)]))])
+        };
+
+        ItemBuilder::default()
+            .title(value.title)
+            .guid(value.guid.map(|g| GuidBuilder::default().value(g).permalink(false).build()))
+            .link(value.link)
+            .comments(value.comments)
+            .pub_date(value.pub_date)
+            .description(value.description)
+            .enclosure(value.enclosure.map(|e| {
+                EnclosureBuilder::default().url(e.url).length(e.length).mime_type(e.mime_type).build()
+            }))
+            .extensions(extensions)
+            .build()
+            .into()
+    }
+}
+
+/// Serializes a list of [`RssItem`]s as [`ReleaseItem`]s, for `#[serde(with = "...")]`.
+pub(crate) mod release_items {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::common::models::{Release, ReleaseItem, RssItem};
+
+    pub fn serialize<S: Serializer>(items: &[RssItem], serializer: S) -> Result<S::Ok, S::Error> {
+        items.iter()
+            .map(|item| ReleaseItem::from(&Release::from_item_ref(item)))
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<RssItem>, D::Error> {
+        let items = Vec::<ReleaseItem>::deserialize(deserializer)?;
+        Ok(items.into_iter().map(|item| Release::from(item).into_item()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::models::{Release, ReleaseItem, SearchResult};
+
+    #[test]
+    fn torznab_attributes() {
+        let input = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:torznab="http://torznab.com/schemas/2015/feed">
+            <channel>
+                <title>Jackett</title>
+                <torznab:response offset="0" total="1"/>
+                <item>
+                    <title>Show.Name.S01E02.1080p.WEB.h264-GROUP</title>
+                    <guid>https://tracker.example/details/42</guid>
+                    <link>magnet:?xt=urn:btih:0123456789ABCDEF0123456789ABCDEF01234567</link>
+                    <pubDate>Sat, 01 Jun 2024 10:00:00 +0000</pubDate>
+                    <size>1073741824</size>
+                    <enclosure url="https://jackett.example/dl/42.torrent" length="1073741824" type="application/x-bittorrent"/>
+                    <torznab:attr name="category" value="5000"/>
+                    <torznab:attr name="category" value="5040"/>
+                    <torznab:attr name="seeders" value="12"/>
+                    <torznab:attr name="peers" value="20"/>
+                    <torznab:attr name="infohash" value="0123456789ABCDEF0123456789ABCDEF01234567"/>
+                    <torznab:attr name="downloadvolumefactor" value="0"/>
+                    <torznab:attr name="uploadvolumefactor" value="1"/>
+                </item>
+            </channel>
+        </rss>"#;
+
+        let result = SearchResult::try_from(input).unwrap();
+        assert_eq!(result.offset.total, Some(1));
+
+        let release = Release::from(result.items[0].clone());
+        assert!(release.is_torrent());
+        assert_eq!(release.size(), Some(1073741824));
+        assert_eq!(release.categories(), vec![5000, 5040]);
+        assert_eq!(release.seeders(), Some(12));
+        assert_eq!(release.leechers(), Some(8));
+        assert_eq!(release.infohash(), Some("0123456789ABCDEF0123456789ABCDEF01234567"));
+        assert!(release.magnet_url().unwrap().starts_with("magnet:?xt=urn:btih:"));
+        assert_eq!(release.download_volume_factor(), Some(0.0));
+        assert_eq!(release.download_url(), Some("https://jackett.example/dl/42.torrent"));
+
+        let json = serde_json::to_value(&release).unwrap();
+        assert_eq!(json["namespace"], "torznab");
+        assert_eq!(json["size"], 1073741824u64);
+        assert_eq!(json["attrs"][2], serde_json::json!({"name": "seeders", "value": "12"}));
+
+        let back = serde_json::from_value::<Release>(json).unwrap();
+        assert_eq!(ReleaseItem::from(&back), ReleaseItem::from(&release));
+        assert_eq!(back.seeders(), Some(12));
+
+        let json = serde_json::to_string(&result).unwrap();
+        let back = serde_json::from_str::<SearchResult>(&json).unwrap();
+        assert_eq!(back.offset, result.offset);
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+    }
+}
@@ -1,26 +1,375 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::fmt::Display;
 
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize};
+
+use crate::common::Function;
+use crate::common::models::{RssItem, NAMESPACES};
+
+/// Attributes every indexer returns without being asked for them.
+const BASE_ATTRS: [&str; 2] = ["category", "size"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchParameters {
     pub q: String,
-    pub limit: Option<u16>,
-    pub offset:Option<u16>,
-    pub params: Option<HashMap<String, String>>
+    pub limit: Option<u32>,
+    pub offset: Option<u64>,
+    pub params: Option<BTreeMap<String, String>>
 }
 
 impl SearchParameters {
-    pub fn with_offset(&mut self, value: u16) -> &Self {
+    pub fn with_offset(&mut self, value: u64) -> &Self {
         self.offset = Some(value);
         self
     }
-    pub fn add_offset(&mut self, value: u16) -> &Self {
-        self.offset = if let Some(cur) = self.offset {Some(cur + value)} else {Some(value)};
+    pub fn add_offset(&mut self, value: u64) -> &Self {
+        self.offset = Some(self.offset.unwrap_or(0).saturating_add(value));
         self
     }
 }
 
-#[derive(Debug, Clone)]
+/// The position of a page within the results, from the `newznab:response` element.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct SearchOffset {
     pub offset: u64,
-    pub total: u64,
-}
\ No newline at end of file
+    /// `None` if the indexer did not report the number of results
+    #[serde(default)]
+    pub total: Option<u64>,
+}
+
+/// Which extended attributes (e.g. `grabs`, `poster` or `usenetdate`) a search requests.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtendedAttributes {
+    /// Whatever the indexer returns by default, usually only `category` and `size`
+    #[default]
+    Default,
+    /// All attributes the indexer knows (`extended=1`)
+    All,
+    /// Exactly the listed attributes (`attrs=`), anything else the indexer returns
+    /// besides `category` and `size` is dropped from the parsed items
+    Only(Vec<String>),
+}
+
+impl ExtendedAttributes {
+    /// The setting a request payload asks for, `None` if it has neither `extended` nor `attrs`.
+    pub fn from_params(params: &BTreeMap<String, String>) -> Option<Self> {
+        if let Some(attrs) = params.get("attrs") {
+            let attrs = attrs.split(',').map(str::trim).filter(|a| !a.is_empty()).map(str::to_string).collect();
+            Some(ExtendedAttributes::Only(attrs))
+        } else if params.get("extended").is_some_and(|e| e.trim() == "1") {
+            Some(ExtendedAttributes::All)
+        } else if params.contains_key("extended") {
+            Some(ExtendedAttributes::Default)
+        } else {
+            None
+        }
+    }
+
+    /// Adds the matching `extended` or `attrs` parameter to `params`.
+    pub fn apply(&self, params: &mut BTreeMap<String, String>) {
+        match self {
+            ExtendedAttributes::Default => {}
+            ExtendedAttributes::All => {
+                params.insert("extended".to_string(), "1".to_string());
+            }
+            ExtendedAttributes::Only(attrs) => {
+                let mut attrs = attrs.iter().map(|a| a.trim().to_lowercase()).collect::<Vec<_>>();
+                attrs.sort();
+                attrs.dedup();
+                params.insert("attrs".to_string(), attrs.join(","));
+            }
+        }
+    }
+
+    /// Returns `true` if attribute `name` is part of the response.
+    pub fn includes(&self, name: &str) -> bool {
+        match self {
+            ExtendedAttributes::Only(attrs) => {
+                BASE_ATTRS.contains(&name) || attrs.iter().any(|a| a.eq_ignore_ascii_case(name))
+            }
+            _ => true,
+        }
+    }
+
+    /// Drops all attributes from `items` that were not requested.
+    pub fn retain(&self, items: &mut [RssItem]) {
+        if !matches!(self, ExtendedAttributes::Only(_)) {
+            return;
+        }
+
+        for item in items {
+            for ns in NAMESPACES {
+                if let Some(attrs) = item.extensions.get_mut(ns).and_then(|ext| ext.get_mut("attr")) {
+                    attrs.retain(|a| a.attrs.get("name").is_some_and(|n| self.includes(n)));
+                }
+            }
+        }
+    }
+}
+
+/// Field a search can be sorted by on the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    Name,
+    Size,
+    Files,
+    /// Number of grabs
+    Stats,
+    Posted,
+    Category,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// The `sort` parameter, e.g. `posted_desc`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sort {
+    pub field: SortField,
+    pub order: SortOrder,
+}
+
+impl Display for Sort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let field = match self.field {
+            SortField::Name => "name",
+            SortField::Size => "size",
+            SortField::Files => "files",
+            SortField::Stats => "stats",
+            SortField::Posted => "posted",
+            SortField::Category => "cat",
+        };
+        let order = match self.order {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        };
+        write!(f, "{}_{}", field, order)
+    }
+}
+
+/// A fluent builder for `t=search` requests with typed Newznab parameters.
+///
+/// ```
+/// use maybe_newznab_client::common::models::{SearchQuery, SortField, SortOrder};
+///
+/// let query = SearchQuery::new("Show Name")
+///     .categories([5040, 5030])
+///     .max_age(7)
+///     .limit(50)
+///     .sort(SortField::Posted, SortOrder::Desc);
+///
+/// assert_eq!(query.to_params()["cat"], "5030,5040");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    q: String,
+    categories: Vec<u32>,
+    limit: Option<u32>,
+    offset: Option<u64>,
+    min_age: Option<u32>,
+    max_age: Option<u32>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    attrs: Vec<String>,
+    extended: bool,
+    sort: Option<Sort>,
+    params: BTreeMap<String, String>,
+}
+
+impl SearchQuery {
+    pub fn new(q: impl AsRef<str>) -> Self {
+        Self {
+            q: q.as_ref().to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn q(mut self, value: impl AsRef<str>) -> Self {
+        self.q = value.as_ref().to_string();
+        self
+    }
+    pub fn category(mut self, value: u32) -> Self {
+        self.categories.push(value);
+        self
+    }
+    pub fn categories(mut self, value: impl IntoIterator<Item = u32>) -> Self {
+        self.categories.extend(value);
+        self
+    }
+    pub fn limit(mut self, value: u32) -> Self {
+        self.limit = Some(value);
+        self
+    }
+    pub fn offset(mut self, value: u64) -> Self {
+        self.offset = Some(value);
+        self
+    }
+    /// Minimum age in days
+    pub fn min_age(mut self, value: u32) -> Self {
+        self.min_age = Some(value);
+        self
+    }
+    /// Maximum age in days
+    pub fn max_age(mut self, value: u32) -> Self {
+        self.max_age = Some(value);
+        self
+    }
+    /// Minimum size in megabytes
+    pub fn min_size(mut self, value: u64) -> Self {
+        self.min_size = Some(value);
+        self
+    }
+    /// Maximum size in megabytes
+    pub fn max_size(mut self, value: u64) -> Self {
+        self.max_size = Some(value);
+        self
+    }
+    /// Requests the given extended attributes, e.g. `grabs` or `poster`
+    pub fn attrs(mut self, value: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.attrs.extend(value.into_iter().map(|a| a.as_ref().to_string()));
+        self
+    }
+    /// Requests all extended attributes
+    pub fn extended(mut self, value: bool) -> Self {
+        self.extended = value;
+        self
+    }
+    pub fn sort(mut self, field: SortField, order: SortOrder) -> Self {
+        self.sort = Some(Sort { field, order });
+        self
+    }
+    /// Any other parameter, typed parameters take precedence
+    pub fn param(mut self, key: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.params.insert(key.as_ref().to_string(), value.as_ref().to_string());
+        self
+    }
+
+    /// All parameters of the query, sorted by name. Lists are sorted and de-duplicated so
+    /// that equal queries always produce equal parameters.
+    pub fn to_params(&self) -> BTreeMap<String, String> {
+        let mut params = self.params.clone();
+
+        params.insert("q".to_string(), self.q.clone());
+        if let Some(limit) = self.limit {
+            params.insert("limit".to_string(), limit.to_string());
+        }
+        if let Some(offset) = self.offset {
+            params.insert("offset".to_string(), offset.to_string());
+        }
+        if !self.categories.is_empty() {
+            params.insert("cat".to_string(), join_sorted(self.categories.iter().map(|c| c.to_string())));
+        }
+        for (key, value) in [("minage", self.min_age), ("maxage", self.max_age)] {
+            if let Some(value) = value {
+                params.insert(key.to_string(), value.to_string());
+            }
+        }
+        for (key, value) in [("minsize", self.min_size), ("maxsize", self.max_size)] {
+            if let Some(value) = value {
+                params.insert(key.to_string(), value.to_string());
+            }
+        }
+        if !self.attrs.is_empty() {
+            params.insert("attrs".to_string(), join_sorted(self.attrs.iter().map(|a| a.trim().to_lowercase())));
+        }
+        if self.extended {
+            params.insert("extended".to_string(), "1".to_string());
+        }
+        if let Some(sort) = self.sort {
+            params.insert("sort".to_string(), sort.to_string());
+        }
+
+        params
+    }
+
+    pub fn build(self) -> SearchParameters {
+        let mut params = self.to_params();
+        let q = params.remove("q").unwrap_or_default();
+        let limit = params.remove("limit").and_then(|l| l.parse().ok());
+        let offset = params.remove("offset").and_then(|o| o.parse().ok());
+
+        SearchParameters {
+            q,
+            limit,
+            offset,
+            params: if params.is_empty() { None } else { Some(params) },
+        }
+    }
+}
+
+impl From<SearchQuery> for SearchParameters {
+    fn from(query: SearchQuery) -> Self {
+        query.build()
+    }
+}
+
+impl From<SearchQuery> for Function {
+    fn from(query: SearchQuery) -> Self {
+        Function::Search(query.build())
+    }
+}
+
+fn join_sorted(values: impl Iterator<Item = String>) -> String {
+    let mut values = values.filter(|v| !v.is_empty()).collect::<Vec<_>>();
+    values.sort();
+    values.dedup();
+    values.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::models::{ExtendedAttributes, Release, SearchQuery, SortField, SortOrder};
+    use crate::mock::MockRelease;
+
+    #[test]
+    fn builds_deterministic_params() {
+        let a = SearchQuery::new("foo")
+            .categories([5040, 5030, 5040])
+            .attrs(["poster", "grabs"])
+            .offset(70_000)
+            .limit(100)
+            .min_size(100)
+            .sort(SortField::Stats, SortOrder::Desc)
+            .param("season", "1");
+        let b = SearchQuery::new("foo")
+            .param("season", "1")
+            .sort(SortField::Stats, SortOrder::Desc)
+            .min_size(100)
+            .limit(100)
+            .offset(70_000)
+            .attrs(["grabs", "poster"])
+            .categories([5030, 5040]);
+
+        assert_eq!(a.to_params(), b.to_params());
+        assert_eq!(
+            a.to_params().into_iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("&"),
+            "attrs=grabs,poster&cat=5030,5040&limit=100&minsize=100&offset=70000&q=foo&season=1&sort=stats_desc"
+        );
+
+        let params = a.build();
+        assert_eq!(params.offset, Some(70_000));
+        assert_eq!(params.limit, Some(100));
+        assert!(!params.params.unwrap().contains_key("q"));
+    }
+
+    #[test]
+    fn extended_attributes() {
+        assert_eq!(ExtendedAttributes::from_params(&SearchQuery::new("").to_params()), None);
+        assert_eq!(ExtendedAttributes::from_params(&SearchQuery::new("").extended(true).to_params()), Some(ExtendedAttributes::All));
+
+        let requested = ExtendedAttributes::from_params(&SearchQuery::new("").attrs(["grabs"]).to_params()).unwrap();
+        assert_eq!(requested, ExtendedAttributes::Only(vec!["grabs".to_string()]));
+
+        let mut items = vec![MockRelease::new("Show").size(100).attr("grabs", "3").attr("poster", "a@b.c").item()];
+
+        requested.retain(&mut items);
+        let release = Release::from_item_ref(&items[0]);
+        assert_eq!(release.size(), Some(100));
+        assert_eq!(release.grabs(), Some(3));
+        assert_eq!(release.poster(), None);
+    }
+}
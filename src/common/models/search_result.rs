@@ -1,16 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 use std::vec::IntoIter;
 use maybe_async::maybe_async;
 use rss::Channel;
-use crate::Client;
+use serde::{Deserialize, Serialize};
+use crate::{Client, Error};
+use crate::filter::ReleaseFilter;
 use crate::common::error::ModelError;
 use crate::common::Function;
-use crate::common::models::{RssItem, SearchOffset, SearchParameters};
+use crate::common::models::{release_items, ExtendedAttributes, RssItem, SearchKind, SearchOffset, SearchParameters};
 
-#[derive(Debug)]
+/// Serializes its items as [`ReleaseItem`](crate::common::models::ReleaseItem)s.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct SearchResult {
     pub offset: SearchOffset,
+    #[serde(with = "release_items")]
     pub items: Vec<rss::Item>,
 }
 
@@ -71,18 +75,16 @@ impl SearchResultItem {
 impl TryFrom<rss::Channel> for SearchResult {
     type Error = ModelError;
 
+    /// Fails if `newznab:response` is malformed, a missing one is treated as a page at
+    /// offset 0 with an unknown total.
     fn try_from(channel: Channel) -> Result<Self, Self::Error> {
-        let res = channel.get_nn_ext();
-        if let Some(mut offset) = channel.get_nn_ext() {
-            Ok(
-                Self {
-                    offset,
-                    items: channel.items,
-                }
-            )
-        } else {
-            Err(ModelError::RssExtensionError("Could not get Newznab Extension from RSS.".to_string()))
-        }
+        let offset: Option<Result<SearchOffset, ModelError>> = channel.get_nn_ext();
+        Ok(
+            Self {
+                offset: offset.transpose()?.unwrap_or_default(),
+                items: channel.items,
+            }
+        )
     }
 }
 
@@ -90,86 +92,414 @@ pub trait GetNewznabExtension<'a, T> {
     fn get_nn_ext(&'a self) -> Option<T>;
 }
 
-impl<'a> GetNewznabExtension<'a, SearchOffset> for rss::Channel {
-    fn get_nn_ext(&'a self) -> Option<SearchOffset> {
-        if let Some(namespace) = self.extensions.get("newznab") {
-            if let Some(ext) = namespace.get("response") {
-                if let Some(response) = ext.get(0) {
-                    Some(
-                        SearchOffset {
-                            offset: response.attrs.get("offset").unwrap().parse().unwrap(),
-                            total: response.attrs.get("total").unwrap().parse().unwrap(),
-                        }
-                    )
-                } else {
-                    None
-                }
-            } else {
-                None
+/// The RSS namespaces the newznab extensions may be found in, Torznab indexers (e.g. Jackett or
+/// Prowlarr) use the same elements in their own namespace.
+pub const NAMESPACES: [&str; 2] = ["newznab", "torznab"];
+
+impl<'a> GetNewznabExtension<'a, Result<SearchOffset, ModelError>> for rss::Channel {
+    /// `None` if the channel has no `newznab:response`, an error if its `offset` or `total`
+    /// is not a number. A missing `offset` is 0, a missing or empty `total` is unknown.
+    fn get_nn_ext(&'a self) -> Option<Result<SearchOffset, ModelError>> {
+        let response = NAMESPACES.iter()
+            .filter_map(|ns| self.extensions.get(*ns))
+            .filter_map(|namespace| namespace.get("response"))
+            .find_map(|ext| ext.first())?;
+
+        let attr = |name: &str| -> Result<Option<u64>, ModelError> {
+            match response.attrs.get(name).map(|v| v.trim()).filter(|v| !v.is_empty()) {
+                Some(value) => value.parse().map(Some).map_err(|_| ModelError::InvalidSearchOffset {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                }),
+                None => Ok(None),
             }
-        } else {
-            None
-        }
+        };
+
+        Some(attr("offset").and_then(|offset| Ok(SearchOffset { offset: offset.unwrap_or(0), total: attr("total")? })))
     }
 }
 
 impl<'a> GetNewznabExtension<'a, HashMap<&'a String, &'a String>> for rss::Item {
     fn get_nn_ext(&'a self) -> Option<HashMap<&'a String, &'a String>> {
-        if let Some(namespace) = self.extensions.get("newznab") {
-            if let Some(attrs) = namespace.get("attr") {
-                Some(
-                    attrs.iter().map(|x| {
-                        (x.attrs.get("name").unwrap(), x.attrs.get("value").unwrap())
-                    }).collect::<HashMap<_, _>>()
-                )
-            } else {
-                None
+        let mut found = false;
+        let mut attrs = HashMap::new();
+
+        for namespace in NAMESPACES.iter().filter_map(|ns| self.extensions.get(*ns)) {
+            if let Some(ext) = namespace.get("attr") {
+                found = true;
+                for x in ext {
+                    if let (Some(name), Some(value)) = (x.attrs.get("name"), x.attrs.get("value")) {
+                        attrs.entry(name).or_insert(value);
+                    }
+                }
             }
-        } else {
-            None
+        }
+
+        if found { Some(attrs) } else { None }
+    }
+}
+
+/// One request of an [`ActiveSearchResult`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageInfo {
+    /// The offset the page was requested at
+    pub offset: u64,
+    /// The `limit` sent, servers may return fewer items even if more results exist
+    pub limit: u32,
+    /// The number of items received
+    pub received: usize,
+    /// The total the server reported with this page
+    pub total: Option<u64>,
+}
+
+impl PageInfo {
+    pub fn new(offset: u64, limit: u32, result: &SearchResult) -> Self {
+        Self { offset, limit, received: result.items.len(), total: result.offset.total }
+    }
+
+    /// Where the next page starts, based on the items received rather than the `limit`.
+    pub fn next_offset(&self) -> u64 {
+        self.offset.saturating_add(self.received as u64)
+    }
+
+    pub fn is_short(&self) -> bool {
+        (self.received as u64) < self.limit as u64
+    }
+
+    /// Whether no results follow this page. A short page only ends the search if the total
+    /// is unknown or not `exact`, servers capping the `limit` return short pages as well.
+    fn is_last(&self, exact_total: bool) -> bool {
+        match self.total.filter(|_| exact_total) {
+            _ if self.received == 0 => true,
+            Some(total) => self.next_offset() >= total,
+            None => self.is_short(),
         }
     }
 }
 
+/// The paging state of an [`ActiveSearchResult`] without its items, to store a search and
+/// continue it later, see [`ActiveSearchResult::cursor`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchCursor {
+    pub api_url: String,
+    pub kind: SearchKind,
+    pub search_parameters: SearchParameters,
+    pub search_offset: SearchOffset,
+    pub attributes: ExtendedAttributes,
+    pub pages: Vec<PageInfo>,
+    exact_total: bool,
+    exhausted: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ActiveSearchResult {
+    /// The api url of the indexer that returned the result
+    #[serde(default)]
+    pub api_url: String,
+    #[serde(default)]
+    pub kind: SearchKind,
     pub search_parameters: SearchParameters,
+    /// The `newznab:response` of the latest page
     pub search_offset: SearchOffset,
+    #[serde(with = "release_items")]
     pub items: Vec<RssItem>,
+    /// The extended attributes the search requested, see [`ExtendedAttributes::retain`]
+    pub attributes: ExtendedAttributes,
+    /// Every page fetched so far, in order
+    #[serde(default)]
+    pub pages: Vec<PageInfo>,
+    #[serde(default = "exact_total")]
+    exact_total: bool,
+    #[serde(default)]
+    exhausted: bool,
+}
+
+fn exact_total() -> bool {
+    true
 }
 
 #[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
 impl ActiveSearchResult {
-    #[maybe_async::maybe_async(AFIT)]
-    pub async fn more(&mut self, client: &Client) {
-        let amount = client.caps.limits.max();
-        self.get_more(client, amount).await
+    /// A result of the single `page`, `exact_total` is `false` if the server's totals are
+    /// estimates (see [`Quirks`](crate::quirks::Quirks)).
+    pub fn new(
+        api_url: impl AsRef<str>,
+        kind: SearchKind,
+        search_parameters: SearchParameters,
+        attributes: ExtendedAttributes,
+        page: PageInfo,
+        result: SearchResult,
+        exact_total: bool,
+    ) -> Self {
+        Self {
+            api_url: api_url.as_ref().to_string(),
+            kind,
+            search_parameters,
+            search_offset: result.offset,
+            items: result.items,
+            attributes,
+            exhausted: page.is_last(exact_total),
+            pages: vec![page],
+            exact_total,
+        }
+    }
+
+    /// Continues a search from its cursor. Items fetched before the cursor was taken are not
+    /// known, so later pages are not checked against them.
+    pub fn from_cursor(cursor: SearchCursor) -> Self {
+        Self {
+            api_url: cursor.api_url,
+            kind: cursor.kind,
+            search_parameters: cursor.search_parameters,
+            search_offset: cursor.search_offset,
+            items: vec![],
+            attributes: cursor.attributes,
+            pages: cursor.pages,
+            exact_total: cursor.exact_total,
+            exhausted: cursor.exhausted,
+        }
+    }
+
+    pub fn cursor(&self) -> SearchCursor {
+        SearchCursor {
+            api_url: self.api_url.clone(),
+            kind: self.kind,
+            search_parameters: self.search_parameters.clone(),
+            search_offset: self.search_offset.clone(),
+            attributes: self.attributes.clone(),
+            pages: self.pages.clone(),
+            exact_total: self.exact_total,
+            exhausted: self.exhausted,
+        }
+    }
+
+    /// Keeps only the items matching `filter`.
+    pub fn retain_matching(&mut self, filter: &ReleaseFilter) {
+        filter.apply(self)
+    }
+
+    /// The function that fetches the page at `offset`.
+    pub fn function_at(&self, offset: u64) -> Function {
+        let mut params = self.search_parameters.clone();
+        params.with_offset(offset);
+        self.kind.function(params)
     }
 
+    /// Where the next page starts.
+    pub fn next_offset(&self) -> u64 {
+        self.pages.last().map_or(
+            self.search_offset.offset.saturating_add(self.items.len() as u64),
+            PageInfo::next_offset,
+        )
+    }
+
+    /// Whether `search_offset.total` is exact, see [`Quirks`](crate::quirks::Quirks).
+    pub fn is_total_exact(&self) -> bool {
+        self.exact_total
+    }
+
+    /// `false` once a page ended the results, see [`PageInfo`].
+    pub fn has_more(&self) -> bool {
+        !self.exhausted
+    }
+
+    /// The number of results not fetched yet, `None` if the server reported no exact total.
+    pub fn remaining(&self) -> Option<u64> {
+        if self.exhausted {
+            return Some(0);
+        }
+        self.search_offset.total
+            .filter(|_| self.exact_total)
+            .map(|total| total.saturating_sub(self.next_offset()))
+    }
+
+    /// Fetches the next page, `None` if there is none.
     #[maybe_async::maybe_async(AFIT)]
-    pub async fn get_more(&mut self, client: &Client, offset: i32) {
+    pub async fn next_page(&mut self, client: &Client) -> Result<Option<PageInfo>, Error> {
+        if !self.has_more() {
+            return Ok(None);
+        }
+        let offset = self.next_offset();
+        let page = client.search(self.function_at(offset)).await?;
+        self.search_parameters.with_offset(offset);
+        Ok(Some(self.append(page)))
+    }
 
-        let total = (self.search_offset.total - self.search_offset.offset).min(offset as u64);
-        let step_size = client.caps.limits.max().min(offset);
+    /// Adds the items of `page` that are not known yet, items move to later pages when new
+    /// releases arrive between two requests. A page of known items only (a server ignoring the
+    /// `offset`) ends the results.
+    fn append(&mut self, page: ActiveSearchResult) -> PageInfo {
+        let info = page.pages.last().cloned().unwrap_or(PageInfo {
+            offset: self.next_offset(),
+            limit: 0,
+            received: page.items.len(),
+            total: page.search_offset.total,
+        });
+        let fresh = {
+            let known = self.items.iter()
+                .filter_map(|item| item.guid.as_ref())
+                .map(|guid| guid.value.as_str())
+                .collect::<HashSet<_>>();
+            page.items.into_iter()
+                .filter(|item| !item.guid.as_ref().is_some_and(|guid| known.contains(guid.value.as_str())))
+                .collect::<Vec<_>>()
+        };
+        let ignored_offset = info.received > 0 && fresh.is_empty();
+        self.items.extend(fresh);
 
-        for _ in (0..total).step_by(step_size as usize) {
-            let left = (self.search_offset.total - self.search_offset.offset).min(step_size as u64);
-            let step = step_size.min(left as i32) as u16;
+        self.exact_total = page.exact_total;
+        self.exhausted = page.exhausted || ignored_offset;
+        self.search_offset = page.search_offset;
+        self.pages.push(info.clone());
+        info
+    }
+
+    /// Fetches one more page.
+    #[maybe_async::maybe_async(AFIT)]
+    pub async fn more(&mut self, client: &Client) -> Result<(), Error> {
+        self.next_page(client).await.map(|_| ())
+    }
 
-            &self.search_parameters.add_offset(step);
-            let next = client.search(Function::Search(self.search_parameters.clone())).await;
-            if let Ok(new) = next {
-                self.items.extend(new.items);
-                self.search_offset = new.search_offset;
+    /// Fetches pages until at least `amount` more items arrived or the results ended.
+    #[maybe_async::maybe_async(AFIT)]
+    pub async fn get_more(&mut self, client: &Client, amount: usize) -> Result<(), Error> {
+        let mut received = 0;
+        while received < amount {
+            match self.next_page(client).await? {
+                Some(page) => received += page.received,
+                None => break,
             }
         }
+        Ok(())
     }
 
+    /// Fetches pages until the results ended.
     #[maybe_async::maybe_async(AFIT)]
-    pub async fn all(&mut self, client: &Client) {
-        let amount = client.caps.limits.max();
-        for _ in (self.search_offset.offset..(self.search_offset.total-amount as u64)).step_by(amount as usize) {
-            self.get_more(client, (self.search_offset.total - self.search_offset.offset).min(amount as u64) as i32).await;
-        }
+    pub async fn all(&mut self, client: &Client) -> Result<(), Error> {
+        while self.next_page(client).await?.is_some() {}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::error::ModelError;
+    use crate::common::Function;
+    use crate::common::models::{ActiveSearchResult, ExtendedAttributes, PageInfo, SearchKind, SearchOffset, SearchQuery, SearchResult};
+    use crate::mock::{MockIndexer, MockRelease};
+
+    fn channel(response: &str) -> String {
+        format!(
+            r#"<rss version="2.0" xmlns:newznab="http://www.newznab.com/DTD/2010/feeds/attributes/">
+            <channel><title>Test</title>{}</channel></rss>"#,
+            response,
+        )
+    }
+
+    #[test]
+    fn parses_search_offset() {
+        let result = SearchResult::try_from(channel(r#"<newznab:response offset="20" total="125"/>"#).as_str()).unwrap();
+        assert_eq!(result.offset, SearchOffset { offset: 20, total: Some(125) });
+
+        let result = SearchResult::try_from(channel(r#"<newznab:response offset="0" total=""/>"#).as_str()).unwrap();
+        assert_eq!(result.offset.total, None);
+        let result = SearchResult::try_from(channel("").as_str()).unwrap();
+        assert_eq!(result.offset, SearchOffset::default());
+
+        let malformed = SearchResult::try_from(channel(r#"<newznab:response offset="0" total="many"/>"#).as_str());
+        assert!(matches!(malformed, Err(ModelError::InvalidSearchOffset { name, .. }) if name == "total"));
+    }
+
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn pages_without_total() {
+        let indexer = MockIndexer::new()
+            .limits(10, 10)
+            .without_total()
+            .releases((0..25).map(|i| MockRelease::new(format!("Show.S01E{:02}", i))));
+        let client = indexer.client();
+
+        let mut result = client.search(SearchQuery::new("show").into()).await.unwrap();
+        assert_eq!((result.items.len(), result.search_offset.total), (10, None));
+
+        result.get_more(&client, 10).await.unwrap();
+        assert_eq!((result.items.len(), result.search_offset.offset), (20, 10));
+
+        result.all(&client).await.unwrap();
+        assert_eq!(result.items.len(), 25);
+        assert_eq!(indexer.requests().iter().filter(|r| r.params.get("t").map(String::as_str) == Some("search")).count(), 3);
+    }
+
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn pages_capped_limits() {
+        let releases = || (0..45).map(|i| MockRelease::new(format!("Show.S01E{:02}", i)));
+        let query = || Function::TvSearch(SearchQuery::new("show").limit(100).build());
+
+        // the limit is clamped to the caps, so full pages are not mistaken for the last one
+        let indexer = MockIndexer::new().limits(20, 20).without_total().releases(releases());
+        let client = indexer.client();
+        let mut result = client.search(query()).await.unwrap();
+        assert_eq!((result.pages[0].limit, result.pages[0].is_short()), (20, false));
+        result.all(&client).await.unwrap();
+        assert_eq!((result.items.len(), result.has_more()), (45, false));
+        assert!(indexer.requests().iter().skip(1).all(|r| r.params.get("limit").map(String::as_str) == Some("20")));
+
+        // a server returning fewer items than its caps allow, the total tells there are more
+        let indexer = MockIndexer::new()
+            .caps(r#"<caps><limits max="100" default="100"/><searching><tv-search available="yes"/></searching><categories/></caps>"#)
+            .limits(20, 20)
+            .releases(releases());
+        let client = indexer.client();
+        let mut result = client.search(query()).await.unwrap();
+        assert_eq!((result.items.len(), result.remaining()), (20, Some(25)));
+        assert!(result.has_more() && result.pages[0].is_short());
+
+        result.all(&client).await.unwrap();
+        assert_eq!(result.items.len(), 45);
+        assert_eq!((result.has_more(), result.remaining()), (false, Some(0)));
+        assert_eq!(result.pages.iter().map(|p| (p.offset, p.received)).collect::<Vec<_>>(), vec![(0, 20), (20, 20), (40, 5)]);
+        assert!(indexer.requests().iter().skip(1).all(|r| r.params.get("t").map(String::as_str) == Some("tvsearch")));
+
+        let next = result.next_page(&client).await.unwrap();
+        assert_eq!(next, None);
+
+        let mut small = client.search(SearchQuery::new("S01E01").into()).await.unwrap();
+        let requests = indexer.requests().len();
+        small.all(&client).await.unwrap();
+        assert_eq!((small.items.len(), small.has_more()), (1, false));
+        assert_eq!(indexer.requests().len(), requests);
+    }
+
+    fn page(offset: u64, guids: &[&str]) -> ActiveSearchResult {
+        let items = guids.iter()
+            .map(|guid| format!("<item><title>{}</title><guid>{}</guid></item>", guid, guid))
+            .collect::<String>();
+        let result = SearchResult::try_from(
+            channel(&format!(r#"<newznab:response offset="{}" total="100"/>{}"#, offset, items)).as_str(),
+        ).unwrap();
+        let info = PageInfo::new(offset, guids.len() as u32, &result);
+        ActiveSearchResult::new("", SearchKind::Search, SearchQuery::new("").build(), ExtendedAttributes::Default, info, result, true)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn skips_shifted_items() {
+        let mut result = page(0, &["a", "b", "c"]);
+
+        // a new upload moved "c" to the second page
+        let info = result.append(page(3, &["c", "d", "e"]));
+        assert_eq!((info.received, result.next_offset()), (3, 6));
+        let guids = result.items.iter().map(|item| item.guid.as_ref().unwrap().value.as_str()).collect::<Vec<_>>();
+        assert_eq!(guids, vec!["a", "b", "c", "d", "e"]);
+        assert!(result.has_more());
+
+        // only known items, the server ignored the offset
+        result.append(page(6, &["a", "b", "c"]));
+        assert_eq!((result.items.len(), result.has_more()), (5, false));
+    }
+}
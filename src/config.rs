@@ -0,0 +1,300 @@
+//! Indexer definitions loaded from a TOML (with the `toml` feature) or JSON file.
+//!
+//! ```toml
+//! [indexers.geek]
+//! url = "https://api.nzbgeek.info"
+//! api_key = "${NZBGEEK_API_KEY}"
+//! priority = 1
+//! categories = [5000, 2000]
+//! rate_limit = { requests = 1, interval_secs = 1, daily_cap = 100 }
+//!
+//! [indexers.hydra]
+//! url = "http://hydra.local:5076"
+//! endpoint = "/api"
+//! api_key = "plain-key"
+//! enabled = false
+//! server = "nzb_hydra2"
+//! ```
+//!
+//! `${NAME}` in an api key is replaced by the environment variable `NAME` when the client is
+//! built, so keys do not have to be stored in the file.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ApiKey, Client, ClientBuilder, Error};
+use crate::aggregator::Aggregator;
+use crate::quirks::ServerSoftware;
+use crate::rate_limit::RateLimit;
+
+/// A set of indexers, keyed by name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub indexers: BTreeMap<String, IndexerConfig>,
+}
+
+/// The definition of a single indexer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexerConfig {
+    pub url: String,
+    /// Defaults to `/api`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// May contain `${NAME}` references to environment variables
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<ApiKey>,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    /// Indexers with lower values come first in an [`Aggregator`], ties are ordered by name
+    #[serde(default)]
+    pub priority: i32,
+    /// The categories searches are restricted to, all if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// The indexer software, detected from the caps if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<ServerSoftware>,
+}
+
+/// See [`RateLimit`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub requests: u32,
+    #[serde(default = "one_second")]
+    pub interval_secs: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_cap: Option<u32>,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn one_second() -> u64 {
+    1
+}
+
+impl From<&RateLimitConfig> for RateLimit {
+    fn from(config: &RateLimitConfig) -> Self {
+        let limit = RateLimit::new(config.requests, Duration::from_secs(config.interval_secs));
+        match config.daily_cap {
+            Some(cap) => limit.daily_cap(cap),
+            None => limit,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_json(data: &str) -> Result<Self, Error> {
+        serde_json::from_str(data).map_err(|e| Error::Config(format!("invalid JSON config: {}", e)))
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(data: &str) -> Result<Self, Error> {
+        toml::from_str(data).map_err(|e| Error::Config(format!("invalid TOML config: {}", e)))
+    }
+
+    /// Reads a `.toml` or `.json` file, other extensions are parsed as JSON.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)?;
+        let is_toml = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+
+        #[cfg(feature = "toml")]
+        if is_toml {
+            return Self::from_toml(&data);
+        }
+        #[cfg(not(feature = "toml"))]
+        if is_toml {
+            return Err(Error::Config(format!("{} needs the `toml` feature", path.display())));
+        }
+
+        Self::from_json(&data)
+    }
+
+    /// The enabled indexers, ordered by priority.
+    pub fn enabled(&self) -> Vec<(&str, &IndexerConfig)> {
+        let mut indexers = self.indexers.iter()
+            .filter(|(_, indexer)| indexer.enabled)
+            .map(|(name, indexer)| (name.as_str(), indexer))
+            .collect::<Vec<_>>();
+        indexers.sort_by_key(|(_, indexer)| indexer.priority);
+        indexers
+    }
+
+    /// Builds the client of the indexer `name`, even if it is disabled.
+    pub fn client(&self, name: &str) -> Result<Client, Error> {
+        let indexer = self.indexers.get(name).ok_or_else(|| Error::Config(format!("unknown indexer '{}'", name)))?;
+        indexer.client_builder()?.try_build()
+    }
+
+    /// Builds the clients of all enabled indexers, ordered by priority.
+    pub fn clients(&self) -> Result<Vec<(String, Client)>, Error> {
+        self.enabled().into_iter()
+            .map(|(name, indexer)| Ok((name.to_string(), indexer.client_builder()?.try_build()?)))
+            .collect()
+    }
+
+    /// An [`Aggregator`] over all enabled indexers.
+    pub fn aggregator(&self) -> Result<Aggregator, Error> {
+        Ok(self.clients()?.into_iter().fold(Aggregator::new(), |aggregator, (name, client)| aggregator.add(name, client)))
+    }
+}
+
+impl IndexerConfig {
+    pub fn new(url: impl AsRef<str>) -> Self {
+        Self {
+            url: url.as_ref().to_string(),
+            endpoint: None,
+            api_key: None,
+            enabled: true,
+            priority: 0,
+            categories: Vec::new(),
+            rate_limit: None,
+            server: None,
+        }
+    }
+
+    /// The api key with environment variables resolved.
+    pub fn resolved_api_key(&self) -> Result<Option<ApiKey>, Error> {
+        self.api_key.as_ref()
+            .map(|key| interpolate(key.expose(), |name| std::env::var(name).ok()).map(ApiKey::from))
+            .transpose()
+    }
+
+    /// A builder set up from this definition, further options can be added before building.
+    pub fn client_builder(&self) -> Result<ClientBuilder, Error> {
+        let mut builder = ClientBuilder::new().url(&self.url).categories(self.categories.iter().copied());
+        if let Some(endpoint) = &self.endpoint {
+            builder = builder.endpoint(endpoint);
+        }
+        if let Some(key) = self.resolved_api_key()? {
+            builder = builder.api_token(key);
+        }
+        if let Some(limit) = &self.rate_limit {
+            builder = builder.rate_limiter(RateLimit::from(limit));
+        }
+        if let Some(server) = self.server {
+            builder = builder.server(server);
+        }
+        Ok(builder)
+    }
+}
+
+/// Replaces `${NAME}` in `value` by the result of `lookup(NAME)`, `$$` is a literal `$`.
+fn interpolate(value: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, Error> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(idx) = rest.find('$') {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        if let Some(after) = rest.strip_prefix("$$") {
+            out.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            let end = after.find('}').ok_or_else(|| Error::Config("unterminated `${` in api key".to_string()))?;
+            let name = &after[..end];
+            let resolved = lookup(name).ok_or_else(|| Error::Config(format!("environment variable '{}' is not set", name)))?;
+            out.push_str(&resolved);
+            rest = &after[end + 1..];
+        } else {
+            out.push('$');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::Error;
+    use crate::common::models::SearchQuery;
+    use crate::config::{interpolate, Config};
+    use crate::mock::{MockIndexer, MockRelease};
+
+    const JSON: &str = r#"{
+        "indexers": {
+            "b": { "url": "http://b.example", "api_key": "plain", "priority": 2 },
+            "a": { "url": "http://a.example", "endpoint": "/newznab/api", "priority": 1, "categories": [5040] },
+            "off": { "url": "http://off.example", "enabled": false },
+            "env": { "url": "http://env.example", "api_key": "${NEWZNAB_TEST_UNSET_KEY}", "rate_limit": { "requests": 2 } }
+        }
+    }"#;
+
+    #[test]
+    fn loads_config() {
+        let config = Config::from_json(JSON).unwrap();
+        let names = config.enabled().into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["env", "a", "b"]);
+
+        let env = &config.indexers["env"];
+        assert_eq!(env.rate_limit.as_ref().unwrap().interval_secs, 1);
+        assert!(matches!(env.resolved_api_key(), Err(Error::Config(_))));
+        assert!(!format!("{:?}", config).contains("plain"));
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(Config::from_json(&json).unwrap(), config);
+        assert!(Config::from_json("{\"indexers\": {\"x\": {}}}").is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn loads_toml() {
+        let config = Config::from_toml(r#"
+            [indexers.geek]
+            url = "https://api.nzbgeek.info"
+            api_key = "key"
+            categories = [5000, 2000]
+            rate_limit = { requests = 1, interval_secs = 2, daily_cap = 100 }
+        "#).unwrap();
+        let geek = &config.indexers["geek"];
+        assert!(geek.enabled);
+        assert_eq!(geek.categories, vec![5000, 2000]);
+        assert_eq!(geek.rate_limit.as_ref().unwrap().daily_cap, Some(100));
+    }
+
+    #[test]
+    fn interpolates_env() {
+        let lookup = |name: &str| (name == "KEY").then(|| "s3cr3t".to_string());
+        assert_eq!(interpolate("${KEY}", lookup).unwrap(), "s3cr3t");
+        assert_eq!(interpolate("pre-${KEY}-$$-$x", lookup).unwrap(), "pre-s3cr3t-$-$x");
+        assert!(interpolate("${OTHER}", lookup).is_err());
+        assert!(interpolate("${KEY", lookup).is_err());
+    }
+
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn builds_clients() {
+        let config = Config::from_json(JSON).unwrap();
+        let indexer = MockIndexer::new()
+            .release(MockRelease::new("Show.S01E01").category(5040))
+            .release(MockRelease::new("Show.S01E02").category(2000));
+
+        let client = config.indexers["a"].client_builder().unwrap()
+            .transport(Arc::new(indexer.clone()))
+            .to_client();
+        assert_eq!(client.get_api_url(), "http://a.example/newznab/api");
+
+        let result = client.search(SearchQuery::new("show").into()).await.unwrap();
+        assert_eq!(result.items.len(), 1);
+        let request = indexer.requests().pop().unwrap();
+        assert_eq!(request.params.get("cat").map(String::as_str), Some("5040"));
+
+        let missing = config.client("unknown");
+        assert!(matches!(missing, Err(Error::Config(_))));
+    }
+}
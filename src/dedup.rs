@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::common::models::Release;
+
+/// A group of releases that were identified as the same upload.
+#[derive(Debug, Clone)]
+pub struct Cluster<T> {
+    /// The first release of the cluster in input order
+    pub representative: T,
+    pub alternates: Vec<T>,
+}
+
+impl<T> Cluster<T> {
+    /// The number of releases, a cluster always has its representative.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        1 + self.alternates.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        std::iter::once(&self.representative).chain(self.alternates.iter())
+    }
+}
+
+/// A release that may come from one of several indexers.
+pub trait Sourced: AsRef<Release> {
+    /// The indexer that returned the release, `None` if all releases come from the same one
+    fn indexer(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl Sourced for Release {}
+
+impl<T: Sourced> Sourced for &T {
+    fn indexer(&self) -> Option<&str> {
+        (**self).indexer()
+    }
+}
+
+/// Clusters releases that are the same upload, e.g. mirrored under different guids or
+/// returned twice because of pagination drift.
+///
+/// Two releases are duplicates if they have the same guid on the same indexer (guids are
+/// only unique within an indexer, see [`Sourced`]), or if their normalized titles
+/// are equal and their size, poster and usenet date agree within the configured tolerances.
+/// Values missing on either release are not compared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deduplicator {
+    size_tolerance: f64,
+    date_tolerance: Duration,
+    compare_poster: bool,
+}
+
+impl Default for Deduplicator {
+    fn default() -> Self {
+        Self {
+            size_tolerance: 0.01,
+            date_tolerance: Duration::from_secs(60 * 60),
+            compare_poster: true,
+        }
+    }
+}
+
+impl Deduplicator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum relative difference in size, `0.01` allows 1%
+    pub fn size_tolerance(mut self, value: f64) -> Self {
+        self.size_tolerance = value.max(0.0);
+        self
+    }
+    /// Maximum difference of the usenet dates
+    pub fn date_tolerance(mut self, value: Duration) -> Self {
+        self.date_tolerance = value;
+        self
+    }
+    pub fn compare_poster(mut self, value: bool) -> Self {
+        self.compare_poster = value;
+        self
+    }
+
+    /// Returns `true` if `a` and `b` are the same upload.
+    pub fn is_duplicate<T: Sourced>(&self, a: &T, b: &T) -> bool {
+        if a.indexer() == b.indexer() {
+            if let (Some(a), Some(b)) = (a.as_ref().guid(), b.as_ref().guid()) {
+                if a == b {
+                    return true;
+                }
+            }
+        }
+
+        let (a, b) = (a.as_ref(), b.as_ref());
+
+        match (a.normalized_title(), b.normalized_title()) {
+            (Some(a), Some(b)) if a == b => {}
+            _ => return false,
+        }
+
+        if let (Some(a), Some(b)) = (a.size(), b.size()) {
+            let diff = a.abs_diff(b) as f64;
+            if diff > a.max(b) as f64 * self.size_tolerance {
+                return false;
+            }
+        }
+
+        if self.compare_poster {
+            if let (Some(a), Some(b)) = (a.poster(), b.poster()) {
+                if !a.eq_ignore_ascii_case(b) {
+                    return false;
+                }
+            }
+        }
+
+        if let (Some(a), Some(b)) = (a.posted(), b.posted()) {
+            if (a - b).abs().to_std().unwrap_or_default() > self.date_tolerance {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Groups `releases` into clusters, keeping the order of their first appearance.
+    pub fn cluster<T: Sourced>(&self, releases: impl IntoIterator<Item = T>) -> Vec<Cluster<T>> {
+        let mut clusters: Vec<Cluster<T>> = vec![];
+        let mut by_guid: HashMap<(Option<String>, String), usize> = HashMap::new();
+        let mut by_title: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for release in releases {
+            let r = release.as_ref();
+            let guid = r.guid().map(|guid| (release.indexer().map(str::to_string), guid.to_string()));
+            let title = r.normalized_title();
+
+            let existing = guid.as_ref()
+                .and_then(|g| by_guid.get(g).copied())
+                .or_else(|| {
+                    title.as_ref()
+                        .and_then(|t| by_title.get(t))
+                        .and_then(|candidates| {
+                            candidates.iter().copied().find(|idx| {
+                                clusters[*idx].iter().any(|other| self.is_duplicate(other, &release))
+                            })
+                        })
+                });
+
+            let idx = match existing {
+                Some(idx) => {
+                    clusters[idx].alternates.push(release);
+                    idx
+                }
+                None => {
+                    clusters.push(Cluster { representative: release, alternates: vec![] });
+                    let idx = clusters.len() - 1;
+                    if let Some(title) = title {
+                        by_title.entry(title).or_default().push(idx);
+                    }
+                    idx
+                }
+            };
+            if let Some(guid) = guid {
+                by_guid.entry(guid).or_insert(idx);
+            }
+        }
+
+        clusters
+    }
+
+    /// Only the representatives of all clusters.
+    pub fn dedup<T: Sourced>(&self, releases: impl IntoIterator<Item = T>) -> Vec<T> {
+        self.cluster(releases).into_iter().map(|c| c.representative).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aggregator::AggregatedRelease;
+    use crate::common::models::Release;
+    use crate::dedup::Deduplicator;
+    use crate::mock::MockRelease;
+
+    fn release(guid: &str, title: &str, size: u64, poster: &str, date: &str) -> Release {
+        MockRelease::new(title).guid(guid).size(size).attr("poster", poster).attr("usenetdate", date).to_release()
+    }
+
+    #[test]
+    fn clusters_single_result_set() {
+        let date = "Sat, 01 Jun 2024 10:00:00 +0000";
+        let releases = vec![
+            release("1", "Show.S01E01.720p-GRP", 1_000_000, "a@b.c", date),
+            release("2", "Show S01E01 720p GRP", 1_005_000, "A@B.C", "Sat, 01 Jun 2024 10:30:00 +0000"),
+            release("3", "Show.S01E01.720p-GRP", 1_200_000, "a@b.c", date),
+            release("4", "Show.S01E01.720p-GRP", 1_000_000, "x@y.z", date),
+            release("1", "Show.S01E01.720p-GRP", 1_000_000, "a@b.c", date),
+            release("5", "Show.S01E01.720p-GRP", 1_000_000, "a@b.c", "Sun, 02 Jun 2024 10:00:00 +0000"),
+        ];
+
+        let clusters = Deduplicator::new().cluster(releases);
+        let guids = clusters.iter()
+            .map(|c| c.iter().map(|r| r.guid().unwrap()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        assert_eq!(guids, vec![vec!["1", "2", "1"], vec!["3"], vec!["4"], vec!["5"]]);
+    }
+
+    #[test]
+    fn clusters_merged_sets() {
+        let date = "Sat, 01 Jun 2024 10:00:00 +0000";
+        let merged = vec![
+            AggregatedRelease { indexer: "a".to_string(), release: release("1", "Movie.2024.1080p-GRP", 10, "p", date), also_on: vec![] },
+            AggregatedRelease { indexer: "b".to_string(), release: release("x", "Movie.2024.1080p-GRP", 10, "p", date), also_on: vec![] },
+        ];
+
+        let deduped = Deduplicator::new().dedup(merged);
+        assert_eq!(deduped.len(), 1);
+        assert_eq!(deduped[0].indexer, "a");
+
+        // the same guid on different indexers is not the same release
+        let merged = vec![
+            AggregatedRelease { indexer: "a".to_string(), release: release("1", "Movie.2024.1080p-GRP", 10, "p", date), also_on: vec![] },
+            AggregatedRelease { indexer: "b".to_string(), release: release("1", "Other.Movie.2023.720p-GRP", 5, "p", date), also_on: vec![] },
+        ];
+        assert_eq!(Deduplicator::new().dedup(merged).len(), 2);
+    }
+}
@@ -23,6 +23,27 @@ pub enum Error {
     #[error("http error({0}): {1}")]
     HttpStatusCode(u16, String),
 
+    #[error("rate limit exhausted, next request allowed in {0:?}")]
+    RateLimited(std::time::Duration),
+
+    #[error("indexer unavailable, cooling down for {0:?}")]
+    Unavailable(std::time::Duration),
+
+    #[error("search panicked: {0}")]
+    Panicked(String),
+
+    #[error("search cancelled")]
+    Cancelled,
+
+    #[error("'{0}' is not a search function")]
+    NotASearch(String),
+
+    #[error("search belongs to {expected}, not {actual}")]
+    IndexerMismatch { expected: String, actual: String },
+
+    #[error("invalid client configuration: {0}")]
+    Config(String),
+
     #[error("input/output error: {0}")]
     Io(#[from] std::io::Error),
 }
\ No newline at end of file
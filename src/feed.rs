@@ -0,0 +1,206 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, Error};
+use crate::common::models::{Release, RssItem};
+
+/// Where a [`FeedPoller`] fetches the newest releases from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeedSource {
+    /// `t=search` with an empty query on the API endpoint
+    #[default]
+    Api,
+    /// The `/rss` endpoint with `dl=1`, `num=` and `cat=`
+    Rss,
+}
+
+/// The position of a [`FeedPoller`] in one feed, i.e. the newest release it has seen.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedCursor {
+    pub last_guid: Option<String>,
+    /// `pubDate` of the newest seen release as unix timestamp
+    pub last_pub_date: Option<i64>,
+    /// Guids seen most recently, used to tell apart releases sharing a `pubDate`
+    #[serde(default)]
+    pub recent_guids: VecDeque<String>,
+}
+
+impl FeedCursor {
+    /// Returns the releases of `items` that are newer than the cursor and advances it.
+    pub fn advance(&mut self, items: Vec<RssItem>, remember: usize) -> Vec<Release> {
+        let seen = self.recent_guids.iter().cloned().collect::<HashSet<_>>();
+        let mut new = vec![];
+
+        for release in items.into_iter().map(Release::from) {
+            let guid = release.guid().or(release.link()).map(str::to_string);
+            let published = release.published().map(|d| d.timestamp());
+
+            if guid.as_ref().is_some_and(|g| seen.contains(g)) {
+                continue;
+            }
+            if let (Some(last), Some(published)) = (self.last_pub_date, published) {
+                if published < last {
+                    continue;
+                }
+            }
+            new.push((guid, published, release));
+        }
+
+        // feeds list the newest release first, remember them oldest first
+        for (guid, published, _) in new.iter().rev() {
+            if let Some(guid) = guid {
+                self.recent_guids.push_back(guid.clone());
+            }
+            if published.is_some() && *published >= self.last_pub_date {
+                self.last_pub_date = *published;
+                self.last_guid = guid.clone();
+            }
+        }
+        while self.recent_guids.len() > remember {
+            self.recent_guids.pop_front();
+        }
+
+        new.into_iter().map(|(_, _, release)| release).collect()
+    }
+}
+
+/// The serializable state of a [`FeedPoller`], one cursor per indexer and category set.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedState {
+    pub cursors: BTreeMap<String, FeedCursor>,
+}
+
+impl FeedState {
+    /// The key of the cursor for `indexer` and `categories`, independent of category order.
+    pub fn key(indexer: &str, categories: &[u32]) -> String {
+        let mut categories = categories.to_vec();
+        categories.sort_unstable();
+        categories.dedup();
+        let cats = categories.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",");
+        format!("{}|{}", indexer, cats)
+    }
+}
+
+/// Polls the category feeds of indexers and returns only releases that were not seen before.
+///
+/// The first poll of a feed returns everything it currently contains. Persist
+/// [`FeedPoller::state`] and restore it with [`FeedPoller::with_state`] to continue where a
+/// previous run stopped.
+#[derive(Debug, Clone)]
+pub struct FeedPoller {
+    source: FeedSource,
+    limit: u32,
+    state: FeedState,
+}
+
+impl Default for FeedPoller {
+    fn default() -> Self {
+        Self {
+            source: FeedSource::default(),
+            limit: 100,
+            state: FeedState::default(),
+        }
+    }
+}
+
+impl FeedPoller {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_state(mut self, state: FeedState) -> Self {
+        self.state = state;
+        self
+    }
+    pub fn source(mut self, value: FeedSource) -> Self {
+        self.source = value;
+        self
+    }
+    /// The amount of releases requested per poll
+    pub fn limit(mut self, value: u32) -> Self {
+        self.limit = value.max(1);
+        self
+    }
+
+    pub fn state(&self) -> &FeedState {
+        &self.state
+    }
+
+    pub fn into_state(self) -> FeedState {
+        self.state
+    }
+
+    pub fn cursor(&self, indexer: &str, categories: &[u32]) -> Option<&FeedCursor> {
+        self.state.cursors.get(&FeedState::key(indexer, categories))
+    }
+
+    /// Forgets the position in a feed, the next poll returns all of its releases again.
+    pub fn reset(&mut self, indexer: &str, categories: &[u32]) {
+        self.state.cursors.remove(&FeedState::key(indexer, categories));
+    }
+
+    /// Fetches the feed of `categories` from `client` and returns the releases that are new
+    /// since the last poll of the same `indexer` and categories.
+    #[maybe_async::maybe_async]
+    pub async fn poll(&mut self, indexer: &str, client: &Client, categories: &[u32]) -> Result<Vec<Release>, Error> {
+        let items = client.feed(self.source, categories, self.limit).await?;
+        let new = self.update(indexer, categories, items);
+        log::debug!("Feed '{}' returned {} new releases", FeedState::key(indexer, categories), new.len());
+        Ok(new)
+    }
+
+    /// Advances the cursor of a feed with already fetched `items`.
+    pub fn update(&mut self, indexer: &str, categories: &[u32], items: Vec<RssItem>) -> Vec<Release> {
+        let remember = self.limit as usize * 2;
+        self.state.cursors
+            .entry(FeedState::key(indexer, categories))
+            .or_default()
+            .advance(items, remember)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::models::RssItem;
+    use crate::feed::{FeedPoller, FeedState};
+    use crate::mock::MockRelease;
+
+    fn item(guid: &str, pub_date: &str) -> RssItem {
+        MockRelease::new(format!("Release {}", guid)).guid(guid).pub_date(pub_date).item()
+    }
+
+    #[test]
+    fn returns_only_new_releases() {
+        let mut poller = FeedPoller::new();
+
+        let first = poller.update("idx", &[5040, 5030], vec![
+            item("b", "Sat, 01 Jun 2024 11:00:00 +0000"),
+            item("a", "Sat, 01 Jun 2024 10:00:00 +0000"),
+        ]);
+        assert_eq!(first.len(), 2);
+
+        let second = poller.update("idx", &[5030, 5040], vec![
+            item("d", "Sat, 01 Jun 2024 12:00:00 +0000"),
+            item("c", "Sat, 01 Jun 2024 11:00:00 +0000"),
+            item("b", "Sat, 01 Jun 2024 11:00:00 +0000"),
+            item("old", "Sat, 01 Jun 2024 09:00:00 +0000"),
+        ]);
+        let guids = second.iter().map(|r| r.guid().unwrap()).collect::<Vec<_>>();
+        assert_eq!(guids, vec!["d", "c"]);
+
+        let cursor = poller.cursor("idx", &[5040, 5030]).unwrap();
+        assert_eq!(cursor.last_guid.as_deref(), Some("d"));
+    }
+
+    #[test]
+    fn state_survives_serialization() {
+        let mut poller = FeedPoller::new();
+        poller.update("idx", &[], vec![item("a", "Sat, 01 Jun 2024 10:00:00 +0000")]);
+
+        let json = serde_json::to_string(poller.state()).unwrap();
+        let mut restored = FeedPoller::new().with_state(serde_json::from_str::<FeedState>(&json).unwrap());
+
+        assert!(restored.update("idx", &[], vec![item("a", "Sat, 01 Jun 2024 10:00:00 +0000")]).is_empty());
+    }
+}
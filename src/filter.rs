@@ -0,0 +1,285 @@
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::common::Function;
+use crate::common::models::{ActiveSearchResult, Caps, Release, RssItem};
+
+const MB: u64 = 1024 * 1024;
+const DAY: u64 = 24 * 60 * 60;
+
+/// A composable set of conditions releases have to fulfill.
+///
+/// Conditions whose information is missing from a release (e.g. `grabs` without extended
+/// attributes) never reject it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReleaseFilter {
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    max_age: Option<Duration>,
+    categories: Option<Vec<u32>>,
+    excluded_words: Vec<String>,
+    min_grabs: Option<u32>,
+    reject_passworded: bool,
+}
+
+impl ReleaseFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Minimum size in bytes
+    pub fn min_size(mut self, value: u64) -> Self {
+        self.min_size = Some(value);
+        self
+    }
+    /// Maximum size in bytes
+    pub fn max_size(mut self, value: u64) -> Self {
+        self.max_size = Some(value);
+        self
+    }
+    pub fn max_age(mut self, value: Duration) -> Self {
+        self.max_age = Some(value);
+        self
+    }
+    /// Only allows these categories, a main category like `5000` includes its subcategories.
+    /// Releases without any category are not rejected.
+    pub fn categories(mut self, value: impl IntoIterator<Item = u32>) -> Self {
+        self.categories.get_or_insert_with(Vec::new).extend(value);
+        self
+    }
+    /// Rejects releases whose title contains one of `value` as a word, case-insensitive
+    pub fn exclude_words(mut self, value: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.excluded_words.extend(value.into_iter().map(|w| w.as_ref().to_lowercase()));
+        self
+    }
+    pub fn min_grabs(mut self, value: u32) -> Self {
+        self.min_grabs = Some(value);
+        self
+    }
+    pub fn reject_passworded(mut self, value: bool) -> Self {
+        self.reject_passworded = value;
+        self
+    }
+
+    /// Combines both filters, a release has to pass the conditions of both.
+    ///
+    /// Categories are intersected. If they do not overlap no category is allowed, so every
+    /// release with a category is rejected, like every other contradicting pair of conditions.
+    pub fn and(self, other: ReleaseFilter) -> Self {
+        let categories = match (self.categories, other.categories) {
+            (Some(a), Some(b)) => {
+                // the more specific category of every overlapping pair
+                let mut both = a.iter()
+                    .flat_map(|a| b.iter().map(move |b| (*a, *b)))
+                    .filter_map(|(a, b)| {
+                        if category_matches(a, b) { Some(b) } else if category_matches(b, a) { Some(a) } else { None }
+                    })
+                    .collect::<Vec<_>>();
+                both.sort_unstable();
+                both.dedup();
+                Some(both)
+            }
+            (a, b) => a.or(b),
+        };
+
+        Self {
+            min_size: self.min_size.max(other.min_size),
+            max_size: min_some(self.max_size, other.max_size),
+            max_age: min_some(self.max_age, other.max_age),
+            categories,
+            excluded_words: self.excluded_words.into_iter().chain(other.excluded_words).collect(),
+            min_grabs: self.min_grabs.max(other.min_grabs),
+            reject_passworded: self.reject_passworded || other.reject_passworded,
+        }
+    }
+
+    pub fn matches<I: Borrow<RssItem>>(&self, release: &Release<I>) -> bool {
+        self.matches_at(release, Utc::now())
+    }
+
+    /// Like [`matches`](Self::matches) with the age of the release calculated relative to `now`.
+    pub fn matches_at<I: Borrow<RssItem>>(&self, release: &Release<I>, now: DateTime<Utc>) -> bool {
+        if let Some(size) = release.size() {
+            if self.min_size.is_some_and(|min| size < min) || self.max_size.is_some_and(|max| size > max) {
+                return false;
+            }
+        }
+
+        if let (Some(max), Some(age)) = (self.max_age, release.age(now)) {
+            if age > max {
+                return false;
+            }
+        }
+
+        if let Some(allowed) = &self.categories {
+            let categories = release.categories();
+            if !categories.is_empty() && !categories.iter().any(|c| allowed.iter().any(|a| category_matches(*a, *c))) {
+                return false;
+            }
+        }
+
+        if !self.excluded_words.is_empty() {
+            let title = release.normalized_title().unwrap_or_default();
+            let mut words = title.split(' ');
+            if words.any(|w| self.excluded_words.iter().any(|e| e == w)) {
+                return false;
+            }
+        }
+
+        if let (Some(min), Some(grabs)) = (self.min_grabs, release.grabs()) {
+            if grabs < min {
+                return false;
+            }
+        }
+
+        !(self.reject_passworded && release.is_passworded() == Some(true))
+    }
+
+    pub fn matches_item(&self, item: &RssItem) -> bool {
+        self.matches(&Release::from_item_ref(item))
+    }
+
+    /// Lazily filters a stream of releases.
+    pub fn filter<'a, I>(&'a self, releases: I) -> impl Iterator<Item = I::Item> + 'a
+        where
+            I: IntoIterator + 'a,
+            I::Item: AsRef<Release>,
+    {
+        let now = Utc::now();
+        releases.into_iter().filter(move |r| self.matches_at(r.as_ref(), now))
+    }
+
+    /// Removes all items of `result` that do not match.
+    pub fn apply(&self, result: &mut ActiveSearchResult) {
+        let now = Utc::now();
+        result.items.retain(|item| self.matches_at(&Release::from_item_ref(item), now));
+    }
+
+    /// The conditions the server can evaluate itself for `function`, as request parameters.
+    ///
+    /// Only parameters listed in the caps' `supportedParams` are returned. Sizes are sent in
+    /// megabytes and ages in days, rounded so that the server never drops a matching release.
+    pub fn server_params(&self, caps: &Caps, function: &Function) -> BTreeMap<String, String> {
+        let mut params = BTreeMap::new();
+        let Some(search) = caps.searching.for_function(function) else {
+            return params;
+        };
+
+        if let Some(min) = self.min_size.filter(|_| search.supports_param("minsize")) {
+            params.insert("minsize".to_string(), (min / MB).to_string());
+        }
+        if let Some(max) = self.max_size.filter(|_| search.supports_param("maxsize")) {
+            params.insert("maxsize".to_string(), max.div_ceil(MB).to_string());
+        }
+        if let Some(age) = self.max_age.filter(|_| search.supports_param("maxage")) {
+            params.insert("maxage".to_string(), age.as_secs().div_ceil(DAY).to_string());
+        }
+        if let Some(categories) = self.categories.as_ref().filter(|c| !c.is_empty() && search.supports_param("cat")) {
+            let cats = categories.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",");
+            params.insert("cat".to_string(), cats);
+        }
+
+        params
+    }
+
+    /// Adds the [`server_params`](Self::server_params) to a search, so the indexer drops
+    /// non-matching releases before they count against the page size.
+    pub fn prepare_search(&self, function: &mut Function, caps: &Caps) {
+        let server_params = self.server_params(caps, function);
+        if let Some(params) = function.search_parameters_mut() {
+            params.params.get_or_insert_with(BTreeMap::new).extend(server_params);
+        }
+    }
+}
+
+/// Returns `true` if `category` is `allowed` or one of its subcategories.
+fn category_matches(allowed: u32, category: u32) -> bool {
+    allowed == category || (allowed.is_multiple_of(1000) && allowed / 1000 == category / 1000)
+}
+
+fn min_some<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::common::Function;
+    use crate::common::models::{Caps, Release, RssItem, SearchParameters};
+    use crate::filter::ReleaseFilter;
+    use crate::mock::MockRelease;
+
+    fn release(title: &str, attrs: &[(&str, &str)]) -> Release {
+        attrs.iter().fold(MockRelease::new(title), |release, (name, value)| release.attr(name, value)).to_release()
+    }
+
+    #[test]
+    fn filters_releases() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 3, 10, 0, 0).unwrap();
+        let filter = ReleaseFilter::new()
+            .min_size(100)
+            .max_age(Duration::from_secs(3 * 24 * 3600))
+            .categories([5000])
+            .exclude_words(["CAM"])
+            .and(ReleaseFilter::new().min_grabs(5).reject_passworded(true));
+
+        assert!(filter.matches_at(&release("Show.S01E01", &[("size", "500"), ("category", "5040"), ("grabs", "10")]), now));
+        assert!(filter.matches_at(&release("Show.S01E01", &[]), now));
+        assert!(!filter.matches_at(&release("Show.S01E01", &[("size", "50")]), now));
+        assert!(!filter.matches_at(&release("Show.S01E01", &[("category", "2040")]), now));
+        assert!(!filter.matches_at(&release("Movie.2024.CAM-GRP", &[]), now));
+        assert!(!filter.matches_at(&release("Show.S01E01", &[("grabs", "1")]), now));
+        assert!(!filter.matches_at(&release("Show.S01E01", &[("password", "1")]), now));
+
+        let later = Utc.with_ymd_and_hms(2024, 6, 10, 10, 0, 0).unwrap();
+        assert!(!filter.matches_at(&release("Show.S01E01", &[]), later));
+
+        // disjoint categories allow none of them
+        let disjoint = ReleaseFilter::new().categories([5000]).and(ReleaseFilter::new().categories([2000]));
+        assert_eq!(disjoint.categories, Some(vec![]));
+        assert!(!disjoint.matches_at(&release("Show.S01E01", &[("category", "5040")]), now));
+        assert!(!disjoint.matches_at(&release("Movie.2024", &[("category", "2040")]), now));
+        let uncategorized = Release::from(RssItem { title: Some("Show.S01E01".to_string()), ..RssItem::default() });
+        assert!(disjoint.matches_at(&uncategorized, now));
+
+        let nested = ReleaseFilter::new().categories([5000]).and(ReleaseFilter::new().categories([5040, 2000]));
+        assert_eq!(nested.categories, Some(vec![5040]));
+    }
+
+    #[test]
+    fn translates_supported_params() {
+        let caps = serde_xml_rs::from_str::<Caps>(r#"
+        <caps>
+            <server title="Test"/>
+            <limits max="100" default="100"/>
+            <searching>
+                <search available="yes" supportedParams="q,cat,maxage,maxsize"/>
+            </searching>
+            <categories>
+                <category id="5000" name="TV"/>
+            </categories>
+        </caps>"#).unwrap();
+
+        let function = Function::Search(SearchParameters { q: String::new(), limit: None, offset: None, params: None });
+        let params = ReleaseFilter::new()
+            .min_size(1)
+            .max_size(1024 * 1024 + 1)
+            .max_age(Duration::from_secs(36 * 3600))
+            .categories([5030, 5040])
+            .server_params(&caps, &function);
+
+        assert_eq!(params.get("minsize"), None);
+        assert_eq!(params.get("maxsize").map(String::as_str), Some("2"));
+        assert_eq!(params.get("maxage").map(String::as_str), Some("2"));
+        assert_eq!(params.get("cat").map(String::as_str), Some("5030,5040"));
+    }
+}
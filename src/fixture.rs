@@ -0,0 +1,306 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+use crate::cache::fnv1a;
+use crate::secret::{is_secret_param, scrub_query_with, ApiKey};
+use crate::transport::{Transport, TransportResponse};
+
+/// Whether a [`Client`](crate::Client) records its traffic or replays recorded traffic,
+/// see [`ClientBuilder::fixtures`](crate::ClientBuilder::fixtures).
+#[derive(Debug, Clone, PartialEq)]
+pub enum FixtureMode {
+    /// Sends requests as usual and saves every request/response pair to the directory
+    Record(PathBuf),
+    /// Answers requests from the fixtures in the directory without any network access
+    Replay(PathBuf),
+}
+
+/// A recorded request together with the response of the indexer.
+///
+/// The api key is removed from the request and from urls in the response before a fixture
+/// is saved, and the host is not part of the request, so fixtures can be shared and
+/// replayed against any base url.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fixture {
+    /// Path of the request url, e.g. `/api`
+    pub path: String,
+    pub params: BTreeMap<String, String>,
+    pub response: TransportResponse,
+}
+
+impl Fixture {
+    pub fn new(url: &str, params: &BTreeMap<String, String>, response: TransportResponse) -> Self {
+        Self {
+            path: url_path(url).to_string(),
+            params: params.iter()
+                .filter(|(k, _)| !is_secret_param(k))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            response,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        serde_json::from_slice(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Writes the fixture to `dir` under its [`file_name`](Self::file_name), creating `dir` if needed.
+    pub fn save(&self, dir: impl AsRef<Path>) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir.as_ref())?;
+        let path = dir.as_ref().join(self.file_name());
+        let data = serde_json::to_vec_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&path, data)?;
+        Ok(path)
+    }
+
+    /// `<function>-<hash of the request>.json`, stable for equal requests
+    pub fn file_name(&self) -> String {
+        let function = self.params.get("t").map_or("request", String::as_str);
+        format!("{}-{:016x}.json", function, fnv1a(self.key().as_bytes()))
+    }
+
+    /// Returns `true` if this fixture answers a request of `url` with `params`.
+    pub fn matches(&self, url: &str, params: &BTreeMap<String, String>) -> bool {
+        self.key() == Fixture::new(url, params, TransportResponse::default()).key()
+    }
+
+    fn key(&self) -> String {
+        let params = self.params.iter()
+            .map(|(k, v)| format!("{}={}", k.trim().to_lowercase(), v.trim()))
+            .collect::<Vec<_>>();
+        format!("{}?{}", self.path.trim_end_matches('/'), params.join("&"))
+    }
+}
+
+/// Replaces secrets in recorded bodies, unlike [`REDACTED`](crate::secret::REDACTED) it needs
+/// no escaping in XML, JSON or urls.
+const RECORDED_PLACEHOLDER: &str = "REDACTED";
+
+/// Forwards requests to another transport and saves every exchange as a [`Fixture`].
+///
+/// Failing to save a fixture is logged and does not fail the request.
+#[derive(Debug, Clone)]
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    dir: PathBuf,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: Arc<dyn Transport>, dir: impl AsRef<Path>) -> Self {
+        Self {
+            inner,
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", maybe_async::maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async::maybe_async)]
+impl Transport for RecordingTransport {
+    async fn get(&self, url: &str, query: &BTreeMap<String, String>) -> Result<TransportResponse, Error> {
+        let response = self.inner.get(url, query).await?;
+
+        let mut recorded = response.clone();
+        recorded.body = scrub_body(&recorded.body, query);
+        let fixture = Fixture::new(url, query, recorded);
+        match fixture.save(&self.dir) {
+            Ok(path) => log::debug!("Recorded fixture {}", path.display()),
+            Err(e) => log::warn!("Failed to record fixture in {}: {}", self.dir.display(), e),
+        }
+
+        Ok(response)
+    }
+}
+
+/// Answers requests from the fixtures in a directory.
+///
+/// Fixtures are looked up by their [`file_name`](Fixture::file_name) first, then every
+/// `*.json` file in the directory is tried, so hand-written fixtures can have any name.
+/// A request without a fixture fails with an [`io::ErrorKind::NotFound`] error.
+#[derive(Debug, Clone)]
+pub struct ReplayTransport {
+    dir: PathBuf,
+}
+
+impl ReplayTransport {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The fixture answering a request of `url` with `params`, if any.
+    pub fn find(&self, url: &str, params: &BTreeMap<String, String>) -> io::Result<Option<Fixture>> {
+        let wanted = Fixture::new(url, params, TransportResponse::default());
+        if let Ok(fixture) = Fixture::load(self.dir.join(wanted.file_name())) {
+            if fixture.matches(url, params) {
+                return Ok(Some(fixture));
+            }
+        }
+
+        let mut paths = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        for path in paths {
+            match Fixture::load(&path) {
+                Ok(fixture) if fixture.matches(url, params) => return Ok(Some(fixture)),
+                Ok(_) => {}
+                Err(e) => log::warn!("Skipping invalid fixture {}: {}", path.display(), e),
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", maybe_async::maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async::maybe_async)]
+impl Transport for ReplayTransport {
+    async fn get(&self, url: &str, query: &BTreeMap<String, String>) -> Result<TransportResponse, Error> {
+        match self.find(url, query)? {
+            Some(fixture) => Ok(fixture.response),
+            None => {
+                let request = Fixture::new(url, query, TransportResponse::default()).key();
+                Err(io::Error::new(io::ErrorKind::NotFound, format!("no fixture for '{}'", request)).into())
+            }
+        }
+    }
+}
+
+/// Removes the secrets sent in `query` from a response `body`, keys also show up outside of
+/// query strings, e.g. in the path of download links.
+fn scrub_body(body: &str, query: &BTreeMap<String, String>) -> String {
+    let keys = query.iter()
+        .filter(|(name, _)| is_secret_param(name))
+        .map(|(_, value)| ApiKey::new(value));
+    keys.fold(scrub_query_with(body, RECORDED_PLACEHOLDER), |body, key| key.scrub_with(&body, RECORDED_PLACEHOLDER))
+}
+
+/// The path of `url` without scheme, host and query string.
+fn url_path(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path = rest.find('/').map_or("/", |idx| &rest[idx..]);
+    path.split('?').next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    use crate::ClientBuilder;
+    use crate::common::Function;
+    use crate::common::models::{RssItem, SearchParameters};
+    use crate::fixture::{scrub_body, Fixture, FixtureMode, ReplayTransport};
+    use crate::transport::TransportResponse;
+    use crate::mock::{MockIndexer, MockRelease};
+
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn records_and_replays() {
+        let dir = std::env::temp_dir().join(format!("newznab-fixtures-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let search = Function::Search(SearchParameters { q: "show".to_string(), limit: None, offset: None, params: None });
+
+        let key = "k3y-5f2a9c";
+        let indexer = MockIndexer::new().api_key(key).release(MockRelease::new("Show.S01E01"));
+        let recording = indexer.client_builder().fixtures(FixtureMode::Record(dir.clone())).to_client();
+        let recorded = recording.search(search.clone()).await.unwrap();
+
+        let files = fs::read_dir(&dir).unwrap().count();
+        assert_eq!(files, 2);
+        for entry in fs::read_dir(&dir).unwrap() {
+            let data = fs::read_to_string(entry.unwrap().path()).unwrap();
+            assert!(!data.contains(key), "api key in {}", data);
+        }
+        assert!(recorded.items[0].link().unwrap().contains(key));
+
+        let replaying = ClientBuilder::new()
+            .url("http://elsewhere.example/")
+            .api_token("other")
+            .fixtures(FixtureMode::Replay(dir.clone()))
+            .to_client();
+        assert_eq!(replaying.known_caps(), recording.known_caps());
+        let replayed = replaying.search(search).await.unwrap();
+        let guids = |items: &[RssItem]| items.iter().map(|i| i.guid().map(|g| g.value().to_string())).collect::<Vec<_>>();
+        assert_eq!(guids(&replayed.items), guids(&recorded.items));
+        assert_eq!(replayed.items[0].link(), Some("http://mock.newznab/getnzb/REDACTED/Show.S01E01"));
+
+        let missing = Function::Search(SearchParameters { q: "other".to_string(), limit: None, offset: None, params: None });
+        let result = replaying.search(missing).await;
+        assert!(result.is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn finds_renamed_fixtures() {
+        let dir = std::env::temp_dir().join(format!("newznab-renamed-fixtures-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let params = BTreeMap::from([
+            ("apikey".to_string(), "recorded".to_string()),
+            ("t".to_string(), "caps".to_string()),
+        ]);
+        let fixture = Fixture::new("https://idx.example/api", &params, TransportResponse::ok("<caps/>"));
+        fs::write(dir.join("caps.json"), serde_json::to_vec(&fixture).unwrap()).unwrap();
+
+        let replay = ReplayTransport::new(&dir);
+        let params = BTreeMap::from([
+            ("apikey".to_string(), "anything".to_string()),
+            ("t".to_string(), "caps".to_string()),
+        ]);
+        assert_eq!(replay.find("https://elsewhere.example/api", &params).unwrap(), Some(fixture));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn drops_secret_params_in_any_case() {
+        let params = BTreeMap::from([
+            ("APIKEY".to_string(), "k1".to_string()),
+            ("ApiKey".to_string(), "k2".to_string()),
+            ("Password".to_string(), "hunter2".to_string()),
+            ("t".to_string(), "search".to_string()),
+        ]);
+        let fixture = Fixture::new("https://idx.example/api", &params, TransportResponse::default());
+        assert_eq!(fixture.params, BTreeMap::from([("t".to_string(), "search".to_string())]));
+
+        let data = serde_json::to_string(&fixture).unwrap();
+        assert!(!["k1", "k2", "hunter2"].iter().any(|secret| data.contains(secret)));
+    }
+
+    #[test]
+    fn scrubs_bodies_without_escaping() {
+        let query = BTreeMap::from([("apikey".to_string(), "k3y".to_string())]);
+        assert_eq!(
+            scrub_body(r#"{"link": "https://idx/getnzb/1.nzb?i=1&r=k3y", "key": "k3y"}"#, &query),
+            r#"{"link": "https://idx/getnzb/1.nzb?i=1&r=REDACTED", "key": "REDACTED"}"#,
+        );
+        assert_eq!(
+            scrub_body("<link>https://idx/dl/k3y/1&amp;apikey=k3y</link>", &query),
+            "<link>https://idx/dl/REDACTED/1&amp;apikey=REDACTED</link>",
+        );
+        assert_eq!(scrub_body("no key & < here", &query), "no key & < here");
+    }
+}
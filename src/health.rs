@@ -0,0 +1,359 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use crate::Error;
+use crate::common::models::NewznabError;
+
+/// Describes when an indexer is considered down and for how long it is left alone.
+///
+/// After `failure_threshold` consecutive failures the indexer is put into a cooldown of
+/// `initial_cooldown`, every further cooldown without a success in between lasts
+/// `multiplier` times longer, up to `max_cooldown`. Errors that will not go away by
+/// themselves, like a suspended account or a disabled API, start a cooldown right away.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthPolicy {
+    failure_threshold: u32,
+    initial_cooldown: Duration,
+    max_cooldown: Duration,
+    multiplier: f64,
+    window: usize,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            initial_cooldown: Duration::from_secs(60),
+            max_cooldown: Duration::from_secs(6 * 60 * 60),
+            multiplier: 2.0,
+            window: 20,
+        }
+    }
+}
+
+impl HealthPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn failure_threshold(mut self, value: u32) -> Self {
+        self.failure_threshold = value.max(1);
+        self
+    }
+    pub fn initial_cooldown(mut self, value: Duration) -> Self {
+        self.initial_cooldown = value;
+        self
+    }
+    pub fn max_cooldown(mut self, value: Duration) -> Self {
+        self.max_cooldown = value;
+        self
+    }
+    pub fn multiplier(mut self, value: f64) -> Self {
+        self.multiplier = value.max(1.0);
+        self
+    }
+    /// The number of recent requests the success rate and latency are computed from
+    pub fn window(mut self, value: usize) -> Self {
+        self.window = value.max(1);
+        self
+    }
+
+    pub fn get_failure_threshold(&self) -> u32 {
+        self.failure_threshold
+    }
+    pub fn get_window(&self) -> usize {
+        self.window
+    }
+
+    /// The length of the `n`th cooldown in a row, starting at 1.
+    pub fn cooldown(&self, n: u32) -> Duration {
+        let factor = self.multiplier.powi(n.saturating_sub(1).min(64) as i32);
+        self.initial_cooldown.mul_f64(factor).min(self.max_cooldown)
+    }
+
+    /// Returns `true` if `error` says something about the indexer, errors caused by the
+    /// request itself (e.g. a missing parameter or an unknown item) or by the client
+    /// (e.g. its own rate limit) are not counted.
+    pub fn is_failure(error: &Error) -> bool {
+        match error {
+            Error::Http(_) | Error::ModelError(_) => true,
+            Error::HttpStatusCode(status, _) => *status >= 500 || matches!(status, 401 | 403 | 429),
+            Error::NewznabError(e) => matches!(e.code(), 100..=199 | 900..=999),
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if `error` starts a cooldown without waiting for the threshold.
+    pub fn is_fatal(error: &Error) -> bool {
+        matches!(
+            error,
+            Error::NewznabError(
+                NewznabError::IncorrectUserCredentials { .. }
+                | NewznabError::AccountSuspended { .. }
+                | NewznabError::ApiDisabled { .. }
+            )
+        )
+    }
+}
+
+/// Whether requests are currently sent to an indexer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HealthState {
+    Healthy,
+    /// The last requests failed, but not often enough for a cooldown
+    Degraded { consecutive_failures: u32 },
+    /// Requests fail with [`Error::Unavailable`] without being sent
+    CoolingDown { remaining: Duration },
+}
+
+/// A snapshot of a [`HealthTracker`], counts and latencies refer to the recent window.
+#[derive(Debug, Clone)]
+pub struct HealthStatus {
+    pub state: HealthState,
+    pub successes: u32,
+    pub failures: u32,
+    pub consecutive_failures: u32,
+    /// The number of cooldowns in a row, the next one lasts [`HealthPolicy::cooldown`]`(cooldowns + 1)`
+    pub cooldowns: u32,
+    pub average_latency: Option<Duration>,
+    pub last_latency: Option<Duration>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_failure: Option<DateTime<Utc>>,
+    /// The message of the last failure
+    pub last_error: Option<String>,
+    /// The last error reported by the indexer itself
+    pub last_newznab_error: Option<NewznabError>,
+}
+
+impl HealthStatus {
+    pub fn is_available(&self) -> bool {
+        !matches!(self.state, HealthState::CoolingDown { .. })
+    }
+
+    /// Successes per request of the recent window, `None` without any requests.
+    pub fn success_rate(&self) -> Option<f64> {
+        let total = self.successes + self.failures;
+        (total > 0).then(|| self.successes as f64 / total as f64)
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Recent requests, `true` for a success
+    samples: VecDeque<(bool, Duration)>,
+    consecutive_failures: u32,
+    cooldowns: u32,
+    cooldown_until: Option<Instant>,
+    last_success: Option<DateTime<Utc>>,
+    last_failure: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    last_newznab_error: Option<NewznabError>,
+}
+
+/// Records the outcome of the requests of a [`Client`](crate::Client) and puts the indexer
+/// into a cooldown when it keeps failing, see [`HealthPolicy`].
+///
+/// Clones share their state, like a [`RateLimiter`](crate::rate_limit::RateLimiter).
+#[derive(Debug, Clone, Default)]
+pub struct HealthTracker {
+    policy: HealthPolicy,
+    state: Arc<Mutex<State>>,
+}
+
+impl HealthTracker {
+    pub fn new(policy: HealthPolicy) -> Self {
+        Self {
+            policy,
+            state: Arc::default(),
+        }
+    }
+
+    pub fn get_policy(&self) -> &HealthPolicy {
+        &self.policy
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn record_success(&self, latency: Duration) {
+        let mut state = self.state();
+        self.push_sample(&mut state, true, latency);
+        state.consecutive_failures = 0;
+        state.cooldowns = 0;
+        state.cooldown_until = None;
+        state.last_success = Some(Utc::now());
+    }
+
+    /// Records a failed request, errors that are not [`HealthPolicy::is_failure`] are ignored.
+    pub fn record_failure(&self, error: &Error, latency: Duration) {
+        self.record_failure_at(error, latency, Instant::now())
+    }
+
+    fn record_failure_at(&self, error: &Error, latency: Duration, now: Instant) {
+        if !HealthPolicy::is_failure(error) {
+            return;
+        }
+
+        let mut state = self.state();
+        self.push_sample(&mut state, false, latency);
+        state.consecutive_failures += 1;
+        state.last_failure = Some(Utc::now());
+        state.last_error = Some(error.to_string());
+        if let Error::NewznabError(e) = error {
+            state.last_newznab_error = Some(e.clone());
+        }
+
+        // once over the threshold, every failure after a cooldown starts the next, longer one
+        if state.consecutive_failures >= self.policy.failure_threshold || HealthPolicy::is_fatal(error) {
+            state.cooldowns += 1;
+            let cooldown = self.policy.cooldown(state.cooldowns);
+            log::warn!("Indexer failed {} times in a row, cooling down for {:?}: {}", state.consecutive_failures, cooldown, error);
+            state.cooldown_until = Some(now + cooldown);
+        }
+    }
+
+    fn push_sample(&self, state: &mut State, success: bool, latency: Duration) {
+        state.samples.push_back((success, latency));
+        while state.samples.len() > self.policy.window {
+            state.samples.pop_front();
+        }
+    }
+
+    /// The time left until requests are sent again, `None` if the indexer is available.
+    pub fn cooldown_remaining(&self) -> Option<Duration> {
+        self.cooldown_remaining_at(Instant::now())
+    }
+
+    fn cooldown_remaining_at(&self, now: Instant) -> Option<Duration> {
+        self.state().cooldown_until
+            .map(|until| until.saturating_duration_since(now))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.cooldown_remaining().is_none()
+    }
+
+    /// Forgets all recorded requests and ends a running cooldown.
+    pub fn reset(&self) {
+        *self.state() = State::default();
+    }
+
+    pub fn status(&self) -> HealthStatus {
+        self.status_at(Instant::now())
+    }
+
+    fn status_at(&self, now: Instant) -> HealthStatus {
+        let remaining = self.cooldown_remaining_at(now);
+        let state = self.state();
+
+        let successes = state.samples.iter().filter(|(success, _)| *success).count() as u32;
+        let latencies = state.samples.iter().map(|(_, latency)| *latency).collect::<Vec<_>>();
+
+        HealthStatus {
+            state: match remaining {
+                Some(remaining) => HealthState::CoolingDown { remaining },
+                None if state.consecutive_failures > 0 => HealthState::Degraded { consecutive_failures: state.consecutive_failures },
+                None => HealthState::Healthy,
+            },
+            successes,
+            failures: state.samples.len() as u32 - successes,
+            consecutive_failures: state.consecutive_failures,
+            cooldowns: state.cooldowns,
+            average_latency: (!latencies.is_empty()).then(|| latencies.iter().sum::<Duration>() / latencies.len() as u32),
+            last_latency: latencies.last().copied(),
+            last_success: state.last_success,
+            last_failure: state.last_failure,
+            last_error: state.last_error.clone(),
+            last_newznab_error: state.last_newznab_error.clone(),
+        }
+    }
+}
+
+impl From<HealthPolicy> for HealthTracker {
+    fn from(policy: HealthPolicy) -> Self {
+        Self::new(policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::Error;
+    use crate::common::models::SearchQuery;
+    use crate::health::{HealthPolicy, HealthState, HealthTracker};
+    use crate::mock::MockIndexer;
+
+    #[test]
+    fn escalates_cooldowns() {
+        let policy = HealthPolicy::new()
+            .failure_threshold(2)
+            .initial_cooldown(Duration::from_secs(10))
+            .max_cooldown(Duration::from_secs(30));
+        assert_eq!(policy.cooldown(1), Duration::from_secs(10));
+        assert_eq!(policy.cooldown(2), Duration::from_secs(20));
+        assert_eq!(policy.cooldown(3), Duration::from_secs(30));
+
+        let tracker = HealthTracker::new(policy);
+        let error = Error::HttpStatusCode(503, String::new());
+        let start = Instant::now();
+        let latency = Duration::from_millis(100);
+
+        tracker.record_success(latency);
+        tracker.record_failure_at(&error, latency, start);
+        assert_eq!(tracker.status_at(start).state, HealthState::Degraded { consecutive_failures: 1 });
+
+        tracker.record_failure_at(&error, latency, start);
+        assert_eq!(tracker.cooldown_remaining_at(start), Some(Duration::from_secs(10)));
+
+        // the first failure after the cooldown starts the next one
+        let later = start + Duration::from_secs(11);
+        assert_eq!(tracker.cooldown_remaining_at(later), None);
+        tracker.record_failure_at(&error, latency, later);
+        assert_eq!(tracker.cooldown_remaining_at(later), Some(Duration::from_secs(20)));
+
+        let status = tracker.status_at(later);
+        assert_eq!((status.successes, status.failures, status.cooldowns), (1, 3, 2));
+        assert_eq!(status.average_latency, Some(latency));
+        assert_eq!(status.success_rate(), Some(0.25));
+
+        tracker.record_success(latency);
+        assert!(tracker.status_at(later).is_available());
+        assert_eq!(tracker.status_at(later).state, HealthState::Healthy);
+    }
+
+    #[test]
+    fn classifies_errors() {
+        let tracker = HealthTracker::default();
+        tracker.record_failure(&Error::Config("bad".to_string()), Duration::ZERO);
+        tracker.record_failure(&Error::HttpStatusCode(404, String::new()), Duration::ZERO);
+        assert_eq!(tracker.status().failures, 0);
+    }
+
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn client_cools_down() {
+        let indexer = MockIndexer::new();
+        let client = indexer.client_builder().health(HealthPolicy::new()).to_client();
+        let requests = indexer.requests().len();
+
+        indexer.fail_next_with_error(101, "Account suspended");
+        let suspended = client.search(SearchQuery::new("show").into()).await;
+        assert!(matches!(suspended, Err(Error::NewznabError(_))));
+
+        let skipped = client.search(SearchQuery::new("show").into()).await;
+        assert!(matches!(skipped, Err(Error::Unavailable(_))));
+        assert_eq!(indexer.requests().len(), requests + 1);
+
+        let status = client.get_health().unwrap().status();
+        assert_eq!(status.last_newznab_error.as_ref().map(|e| e.code()), Some(101));
+        assert!(!status.is_available());
+    }
+}
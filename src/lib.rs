@@ -17,9 +17,29 @@ mod error;
 pub use error::Error;
 
 mod client;
+pub mod retry;
+pub mod rate_limit;
+pub mod health;
+pub mod quirks;
+pub mod cache;
+pub mod aggregator;
+pub mod session;
+pub mod feed;
+pub mod release_name;
+pub mod filter;
+pub mod ranking;
+pub mod dedup;
+pub mod transport;
+pub mod http;
+pub mod secret;
+pub mod fixture;
+pub mod config;
+#[cfg(any(test, feature = "test-support"))]
+pub mod mock;
 
 
 pub use client::*;
+pub use secret::ApiKey;
 
 pub type NewznabClientBuilder = ClientBuilder;
 pub type NewznabClient = Client;
@@ -27,31 +47,52 @@ pub type NewznabClient = Client;
 
 #[cfg(test)]
 mod tests {
-    use crate::client::{Client, ClientBuilder};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::{Error, RequestOptions};
+    use crate::cache::MemoryCache;
     use crate::common::Function;
-    use crate::common::models::SearchParameters;
+    use crate::common::models::{Release, SearchParameters};
+    use crate::mock::{MockFailure, MockIndexer, MockRelease};
+    use crate::retry::RetryPolicy;
+
+    fn search(q: &str) -> Function {
+        Function::Search(SearchParameters {
+            q: q.to_string(),
+            limit: None,
+            offset: None,
+            params: None,
+        })
+    }
+
+    fn indexer() -> MockIndexer {
+        MockIndexer::new()
+            .api_key("secret")
+            .limits(50, 50)
+            .releases((0..120).map(|i| MockRelease::new(format!("One.Piece.E{:04}.German.1080p-GRP", i))))
+            .release(MockRelease::new("Other.Show.S01E01.720p-GRP").category(5040))
+    }
 
     #[maybe_async::test(
         feature="sync",
         async(all(not(feature="sync"), feature="async"), async_std::test),
     )]
     async fn test_nn_client() {
-        let mut client = ClientBuilder::new()
-            .url("http://search.arr.reinet.xyz:80/")
-            .endpoint("/api")
-            .api_token("6PMLKMJMJTML0URBRSTD9PFOHA")
-            .to_client();
+        let indexer = indexer();
+        let client = indexer.client();
 
-        println!("api_url: {}", client.get_api_url());
-        println!("api_key: {:?}", client.get_api_key());
-        println!("default_payload: {:?}", client.get_default_payload());
-
-        // let res = client.function(Function::Caps, OutputFormat::Xml);
-        // println!("response: {:?}", client.get_default_payload());
+        assert_eq!(client.get_api_url(), "http://mock.newznab/api");
+        assert_eq!(client.redacted_payload().get("apikey").map(String::as_str), Some("<redacted>"));
+        #[allow(deprecated)]
+        let payload = client.get_default_payload();
+        assert_eq!(payload.get("apikey").map(String::as_str), Some("secret"));
+        assert!(!format!("{:?}", client).contains("secret"));
+        assert_eq!(client.known_caps().limits.max(), 50);
 
         let caps = client.get_caps().await.unwrap();
-
-        println!("");
+        assert!(caps.searching.search().unwrap().supports_param("cat"));
+        assert_eq!(indexer.requests().len(), 2);
     }
 
     #[maybe_async::test(
@@ -59,22 +100,49 @@ mod tests {
         async(all(not(feature="sync"), feature="async"), async_std::test),
     )]
     async fn test_nn_search() {
-        let mut client = ClientBuilder::new()
-            .url("http://search.arr.reinet.xyz:80/")
-            .endpoint("/api")
-            .api_token("6PMLKMJMJTML0URBRSTD9PFOHA")
-            .to_client();
+        let client = indexer().client();
 
-        println!("api_url: {}", client.get_api_url());
-        println!("api_key: {:?}", client.get_api_key());
-        println!("default_payload: {:?}", client.get_default_payload());
+        let result = client.search(search("other show")).await.unwrap();
+        assert_eq!(result.search_offset.total, Some(1));
+        assert_eq!(result.items[0].title(), Some("Other.Show.S01E01.720p-GRP"));
 
-        // let res = client.function(Function::Caps, OutputFormat::Xml);
-        // println!("response: {:?}", client.get_default_payload());
+        let mut by_category = search("");
+        if let Function::Search(p) = &mut by_category {
+            p.params = Some([("cat".to_string(), "5040".to_string())].into());
+        }
+        let result = client.search(by_category).await.unwrap();
+        assert_eq!(result.items.len(), 1);
+    }
 
-        let caps = client.get_caps().await.unwrap();
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn test_torznab_search() {
+        let indexer = MockIndexer::new()
+            .torznab()
+            .limits(10, 10)
+            .releases((0..15).map(|i| {
+                MockRelease::new(format!("Show.S01E{:02}.1080p-GRP", i))
+                    .attr("seeders", "12")
+                    .attr("infohash", format!("{:040}", i))
+                    .attr("magneturl", format!("magnet:?xt=urn:btih:{:040}", i))
+                    .attr("downloadvolumefactor", "0")
+            }));
+        let client = indexer.client();
+        assert!(client.known_caps().searching.music_search().unwrap().is_available());
+
+        let mut result = client.search(search("show")).await.unwrap();
+        assert_eq!(result.search_offset.total, Some(15));
+        result.all(&client).await.unwrap();
+        assert_eq!(result.items.len(), 15);
 
-        println!("");
+        let release = Release::from_item_ref(&result.items[3]);
+        assert!(release.is_torrent());
+        assert_eq!(release.seeders(), Some(12));
+        assert_eq!(release.infohash(), Some(format!("{:040}", 3).as_str()));
+        assert!(release.magnet_url().unwrap().starts_with("magnet:?xt=urn:btih:"));
+        assert_eq!(release.download_volume_factor(), Some(0.0));
     }
 
     #[maybe_async::test(
@@ -82,40 +150,108 @@ mod tests {
         async(all(not(feature="sync"), feature="async"), async_std::test),
     )]
     async fn test_active_search() {
-        let mut client = ClientBuilder::new()
-            .url("http://search.arr.reinet.xyz:80/")
-            .endpoint("/api")
-            .api_token("6PMLKMJMJTML0URBRSTD9PFOHA")
-            .to_client();
+        let indexer = indexer();
+        let client = indexer.client();
 
-        println!("api_url: {}", client.get_api_url());
-        println!("api_key: {:?}", client.get_api_key());
+        let mut result = client.search(search("One Piece german")).await.unwrap();
+        assert_eq!((result.search_offset.offset, result.search_offset.total), (0, Some(120)));
+        assert_eq!(result.items.len(), 50);
 
+        result.more(&client).await.unwrap();
+        assert_eq!(result.search_offset.offset, 50);
+        result.more(&client).await.unwrap();
+        assert_eq!(result.search_offset.offset, 100);
+        assert_eq!(result.items.len(), 120);
+        assert_eq!(result.items[119].title(), Some("One.Piece.E0119.German.1080p-GRP"));
 
-        let mut sr = client.search(Function::Search (SearchParameters {
-            q: "One Piece german".to_string(),
-            limit: Some(100),
-            offset: None,
-            params: None
-        })).await;
-
-        if let Ok(mut ar) = sr {
-            println!("ar_offset: {} of {}", ar.search_offset.offset, ar.search_offset.total);
-            ar.more(&client).await;
-            println!("ar_offset: {} of {}", ar.search_offset.offset, ar.search_offset.total);
-            ar.more(&client).await;
-            println!("ar_offset: {} of {}", ar.search_offset.offset, ar.search_offset.total);
-            ar.more(&client).await;
-            println!("ar_offset: {} of {}", ar.search_offset.offset, ar.search_offset.total);
-
-            ar.get_more(&client, 1000).await;
-            println!("ar_offset: {} of {}", ar.search_offset.offset, ar.search_offset.total);
-
-            // ar.all(&client).await;
-            // println!("ar_offset: {} of {}", ar.search_offset.offset, ar.search_offset.total);
+        let offsets = indexer.requests().iter()
+            .filter_map(|r| r.params.get("offset").cloned())
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, vec!["50", "100"]);
+    }
+
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn test_functions() {
+        let indexer = indexer();
+        let client = indexer.client();
+
+        let tv = Function::TvSearch(SearchParameters { q: "one piece".to_string(), limit: Some(10), offset: None, params: None });
+        let result = client.search(tv).await.unwrap();
+        assert_eq!(result.items.len(), 10);
+        assert_eq!(indexer.requests().last().unwrap().params.get("t").map(String::as_str), Some("tvsearch"));
+
+        let release = client.details("Other.Show.S01E01.720p-GRP").await.unwrap().unwrap();
+        assert_eq!(release.categories(), vec![5040]);
+        let missing = client.details("missing").await;
+        assert!(matches!(missing, Err(Error::NewznabError(_))));
+
+        let nzb = client.get_nzb("One.Piece.E0001.German.1080p-GRP").await.unwrap();
+        assert!(nzb.contains("<nzb "));
+    }
+
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn test_error_mapping() {
+        let indexer = indexer();
+        let client = indexer.client();
+
+        indexer.fail_next_with_status(503);
+        let result = client.search(search("")).await;
+        assert!(matches!(result, Err(Error::HttpStatusCode(503, _))));
+
+        indexer.fail_next_with_error(900, "Unknown error");
+        match client.search(search("")).await {
+            Err(Error::NewznabError(e)) => assert_eq!(e.code(), 900),
+            other => panic!("unexpected {:?}", other.map(|r| r.items.len())),
         }
 
-        println!();
+        let unauthorized = indexer.client_builder().api_token("wrong").to_client();
+        match unauthorized.search(search("")).await {
+            Err(Error::NewznabError(e)) => assert_eq!(e.code(), 100),
+            other => panic!("unexpected {:?}", other.map(|r| r.items.len())),
+        }
+
+        let retrying = indexer.client_builder()
+            .retry(RetryPolicy::new().max_attempts(3).initial_backoff(Duration::from_millis(1)).jitter(0.0))
+            .to_client();
+        indexer.fail_next(MockFailure::Status { status: 429, retry_after: None });
+        indexer.fail_next_with_status(502);
+        let result = retrying.search(search("other")).await.unwrap();
+        assert_eq!(result.items.len(), 1);
+    }
+
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn test_cache() {
+        let indexer = indexer();
+        let client = indexer.client_builder()
+            .cache(Arc::new(MemoryCache::new(10, Duration::from_secs(60))))
+            .to_client();
+        let requests = || indexer.requests().iter().filter(|r| r.params.get("t").map(String::as_str) == Some("search")).count();
+
+        indexer.fail_next_with_status(503);
+        let unavailable = client.search(search("other")).await;
+        assert!(unavailable.is_err());
+        indexer.fail_next_with_error(900, "Unknown error");
+        let failed = client.search(search("other")).await;
+        assert!(failed.is_err());
+        assert_eq!(requests(), 2);
+
+        // errors are not cached, so the next search reaches the indexer
+        let first = client.search(search("other")).await.unwrap();
+        let cached = client.search(search("other")).await.unwrap();
+        assert_eq!((first.items.len(), cached.items.len(), requests()), (1, 1, 3));
 
+        let bypassed = client.search_with(search("other"), &RequestOptions::bypass_cache()).await.unwrap();
+        assert_eq!((bypassed.items.len(), requests()), (1, 4));
+        client.search(search("other")).await.unwrap();
+        assert_eq!(requests(), 4);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,476 @@
+//! An in-process mock indexer for tests, enabled by the `test-support` feature.
+//!
+//! [`MockIndexer`] implements [`Transport`], so it answers the requests of a [`Client`]
+//! without any network access:
+//!
+//! ```ignore
+//! use maybe_newznab_client::mock::{MockIndexer, MockRelease};
+//!
+//! let indexer = MockIndexer::new()
+//!     .limits(50, 25)
+//!     .releases((0..120).map(|i| MockRelease::new(format!("Show.S01E{:03}.720p-GRP", i))));
+//! let client = indexer.client();
+//!
+//! indexer.fail_next_with_status(503);
+//! assert_eq!(client.known_caps().limits.max(), 50);
+//! ```
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use crate::{ApiKey, Client, ClientBuilder, Error};
+use crate::common::models::{Release, RssItem};
+use crate::transport::{Transport, TransportResponse};
+
+/// The base URL of clients built by [`MockIndexer::client`]
+pub const MOCK_URL: &str = "http://mock.newznab";
+
+const DEFAULT_DATE: &str = "Sat, 01 Jun 2024 10:00:00 +0000";
+
+/// A release served by a [`MockIndexer`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockRelease {
+    pub title: String,
+    /// Defaults to the title
+    pub guid: Option<String>,
+    pub size: u64,
+    pub category: u32,
+    pub pub_date: String,
+    /// Additional `newznab:attr` elements
+    pub attrs: BTreeMap<String, String>,
+}
+
+impl MockRelease {
+    pub fn new(title: impl AsRef<str>) -> Self {
+        Self {
+            title: title.as_ref().to_string(),
+            guid: None,
+            size: 1024 * 1024 * 1024,
+            category: 5000,
+            pub_date: DEFAULT_DATE.to_string(),
+            attrs: BTreeMap::new(),
+        }
+    }
+
+    pub fn guid(mut self, value: impl AsRef<str>) -> Self {
+        self.guid = Some(value.as_ref().to_string());
+        self
+    }
+    /// Size in bytes
+    pub fn size(mut self, value: u64) -> Self {
+        self.size = value;
+        self
+    }
+    pub fn category(mut self, value: u32) -> Self {
+        self.category = value;
+        self
+    }
+    /// RFC 2822 date, e.g. `Sat, 01 Jun 2024 10:00:00 +0000`
+    pub fn pub_date(mut self, value: impl AsRef<str>) -> Self {
+        self.pub_date = value.as_ref().to_string();
+        self
+    }
+    pub fn attr(mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.attrs.insert(name.as_ref().to_string(), value.as_ref().to_string());
+        self
+    }
+
+    fn matches(&self, query: &BTreeMap<String, String>) -> bool {
+        let title = self.title.to_lowercase();
+        let q = query.get("q").map(|q| q.to_lowercase()).unwrap_or_default();
+        if !q.split_whitespace().all(|word| title.contains(word)) {
+            return false;
+        }
+
+        match query.get("cat").filter(|c| !c.is_empty()) {
+            Some(cats) => cats.split(',')
+                .filter_map(|c| c.trim().parse::<u32>().ok())
+                .any(|c| c == self.category || (c % 1000 == 0 && c / 1000 == self.category / 1000)),
+            None => true,
+        }
+    }
+
+    fn id(&self) -> &str {
+        self.guid.as_ref().unwrap_or(&self.title)
+    }
+
+    /// The item a [`MockIndexer`] without api key serves for this release, for tests of
+    /// code that works on parsed items.
+    pub fn item(&self) -> RssItem {
+        let mut xml = String::new();
+        self.write_item(&mut xml, None, "newznab");
+        let channel = format!(
+            "<rss version=\"2.0\" xmlns:newznab=\"http://www.newznab.com/DTD/2010/feeds/attributes/\">\
+            <channel>{}</channel></rss>",
+            xml,
+        );
+        let mut channel = rss::Channel::from_str(&channel).expect("mock items are valid RSS");
+        channel.items.remove(0)
+    }
+
+    /// [`item`](Self::item) as a [`Release`]
+    pub fn to_release(&self) -> Release {
+        self.item().into()
+    }
+
+    /// Download links contain the api key like on real indexers, in the path of the link and
+    /// in the query of the enclosure.
+    fn write_item(&self, xml: &mut String, key: Option<&ApiKey>, namespace: &str) {
+        let guid = escape(self.id());
+        let (link, download) = match key {
+            Some(key) => {
+                let key = escape(key.expose());
+                (format!("{}/getnzb/{}/{}", MOCK_URL, key, guid), format!("{}/getnzb/{}.nzb?i=1&amp;r={}", MOCK_URL, guid, key))
+            }
+            None => (format!("{}/getnzb/{}", MOCK_URL, guid), format!("{}/getnzb/{}.nzb", MOCK_URL, guid)),
+        };
+        let _ = write!(
+            xml,
+            "<item><title>{}</title><guid>{}</guid><link>{}</link><pubDate>{}</pubDate>\
+            <enclosure url=\"{}\" length=\"{}\" type=\"application/x-nzb\"/>",
+            escape(&self.title), guid, link, escape(&self.pub_date), download, self.size,
+        );
+
+        let mut attrs = BTreeMap::from([
+            ("category".to_string(), self.category.to_string()),
+            ("size".to_string(), self.size.to_string()),
+        ]);
+        attrs.extend(self.attrs.clone());
+        for (name, value) in attrs {
+            let _ = write!(xml, "<{}:attr name=\"{}\" value=\"{}\"/>", namespace, escape(&name), escape(&value));
+        }
+        xml.push_str("</item>");
+    }
+}
+
+/// A request received by a [`MockIndexer`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockRequest {
+    pub url: String,
+    pub params: BTreeMap<String, String>,
+}
+
+/// A canned failure, returned instead of the regular response
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockFailure {
+    /// An HTTP error status, optionally with a `Retry-After` header in seconds
+    Status { status: u16, retry_after: Option<u64> },
+    /// A Newznab `<error code=".." description=".."/>` response
+    Newznab { code: u16, description: String },
+    /// Panics in the transport, like a bug in a custom one
+    Panic,
+}
+
+#[derive(Debug)]
+struct State {
+    caps: Option<String>,
+    max_limit: u32,
+    default_limit: u32,
+    reports_total: bool,
+    offset_in_pages: bool,
+    torznab: bool,
+    supported_params: Vec<String>,
+    api_key: Option<ApiKey>,
+    releases: Vec<MockRelease>,
+    failures: VecDeque<MockFailure>,
+    requests: Vec<MockRequest>,
+}
+
+/// A deterministic Newznab indexer that lives in memory.
+///
+/// It serves generated (or configured) caps, paginated search results filtered by `q` and
+/// `cat`, the RSS feed and canned failures. Clones share their state, so keep a clone
+/// around to inspect the [`requests`](Self::requests) a client made.
+#[derive(Clone)]
+pub struct MockIndexer {
+    state: Arc<Mutex<State>>,
+}
+
+impl std::fmt::Debug for MockIndexer {
+    /// Only the sizes, received requests contain the api key.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state();
+        f.debug_struct("MockIndexer")
+            .field("releases", &state.releases.len())
+            .field("requests", &state.requests.len())
+            .finish()
+    }
+}
+
+impl Default for MockIndexer {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                caps: None,
+                max_limit: 100,
+                default_limit: 100,
+                reports_total: true,
+                offset_in_pages: false,
+                torznab: false,
+                supported_params: vec!["q".to_string(), "cat".to_string()],
+                api_key: None,
+                releases: vec![],
+                failures: VecDeque::new(),
+                requests: vec![],
+            })),
+        }
+    }
+}
+
+impl MockIndexer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves `xml` verbatim as the caps response instead of generating it
+    pub fn caps(self, xml: impl AsRef<str>) -> Self {
+        self.state().caps = Some(xml.as_ref().to_string());
+        self
+    }
+    /// The page sizes advertised in the generated caps, larger `limit`s are capped at `max`
+    pub fn limits(self, max: u32, default: u32) -> Self {
+        {
+            let mut state = self.state();
+            state.max_limit = max.max(1);
+            state.default_limit = default.clamp(1, max.max(1));
+        }
+        self
+    }
+    /// Leaves `newznab:response` out of search results, like indexers that do not count them
+    pub fn without_total(self) -> Self {
+        self.state().reports_total = false;
+        self
+    }
+    /// Reads `offset` as a page number like Spotweb, `newznab:response` still reports items
+    pub fn offset_in_pages(self) -> Self {
+        self.state().offset_in_pages = true;
+        self
+    }
+    /// Answers like a Torznab indexer, with `torznab` attributes and a `music-search` in the
+    /// generated caps
+    pub fn torznab(self) -> Self {
+        self.state().torznab = true;
+        self
+    }
+    /// The `supportedParams` of the generated caps, `q,cat` by default
+    pub fn supported_params(self, value: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.state().supported_params = value.into_iter().map(|p| p.as_ref().to_string()).collect();
+        self
+    }
+    /// Rejects requests without this `apikey` with error `100`
+    pub fn api_key(self, value: impl AsRef<str>) -> Self {
+        self.state().api_key = Some(ApiKey::new(value));
+        self
+    }
+    pub fn release(self, value: MockRelease) -> Self {
+        self.state().releases.push(value);
+        self
+    }
+    pub fn releases(self, value: impl IntoIterator<Item = MockRelease>) -> Self {
+        self.state().releases.extend(value);
+        self
+    }
+    /// Queues a failure for the next request, queued failures are returned in order.
+    ///
+    /// Building a client requests the caps, so queue failures after the client exists.
+    pub fn fail_next(&self, value: MockFailure) {
+        self.state().failures.push_back(value);
+    }
+    pub fn fail_next_with_status(&self, status: u16) {
+        self.fail_next(MockFailure::Status { status, retry_after: None })
+    }
+    pub fn fail_next_with_error(&self, code: u16, description: impl AsRef<str>) {
+        self.fail_next(MockFailure::Newznab { code, description: description.as_ref().to_string() })
+    }
+
+    /// All requests received so far, oldest first
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state().requests.clone()
+    }
+    pub fn clear_requests(&self) {
+        self.state().requests.clear();
+    }
+
+    /// A builder for a client that talks to this indexer, the caps are fetched when the
+    /// client is built.
+    pub fn client_builder(&self) -> ClientBuilder {
+        let builder = ClientBuilder::new()
+            .url(MOCK_URL)
+            .transport(Arc::new(self.clone()));
+        match self.state().api_key.clone() {
+            Some(key) => builder.api_token(key),
+            None => builder,
+        }
+    }
+
+    pub fn client(&self) -> Client {
+        self.client_builder().to_client()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn respond(&self, url: &str, params: &BTreeMap<String, String>) -> TransportResponse {
+        let mut state = self.state();
+        state.requests.push(MockRequest { url: url.to_string(), params: params.clone() });
+
+        if let Some(failure) = state.failures.pop_front() {
+            return match failure {
+                MockFailure::Status { status, retry_after } => {
+                    let response = TransportResponse::ok("").with_status(status);
+                    match retry_after {
+                        Some(secs) => response.with_header("Retry-After", secs.to_string()),
+                        None => response,
+                    }
+                }
+                MockFailure::Newznab { code, description } => error_response(code, &description),
+                MockFailure::Panic => {
+                    drop(state);
+                    panic!("mock indexer panicked");
+                }
+            };
+        }
+
+        if let Some(key) = &state.api_key {
+            let given = params.get("apikey").or_else(|| params.get("r"));
+            if given.map(String::as_str) != Some(key.expose()) {
+                return error_response(100, "Incorrect user credentials");
+            }
+        }
+
+        match params.get("t").map(String::as_str) {
+            Some("caps") => TransportResponse::ok(state.caps_xml()),
+            Some("search" | "tvsearch" | "movie" | "music" | "book") => TransportResponse::ok(state.search_xml(params)),
+            Some("details") => match state.release(params) {
+                Some(release) => TransportResponse::ok(state.channel_xml(Some((0, 1)), [release])),
+                None => error_response(300, "No such item"),
+            },
+            Some("get") => match state.release(params) {
+                Some(release) => TransportResponse::ok(nzb_xml(release)),
+                None => error_response(300, "No such item"),
+            },
+            Some("user") => TransportResponse::ok(format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                <user username=\"{}\" grabs=\"0\" apirequests=\"{}\" role=\"user\"/>",
+                escape(params.get("username").map_or("mock", String::as_str)), state.requests.len(),
+            )),
+            Some(_) => error_response(202, "No such function"),
+            None if params.contains_key("dl") || params.contains_key("num") => {
+                let mut params = params.clone();
+                if let Some(num) = params.remove("num") {
+                    params.insert("limit".to_string(), num);
+                }
+                TransportResponse::ok(state.search_xml(&params))
+            }
+            None => error_response(200, "Missing parameter"),
+        }
+    }
+}
+
+impl State {
+    fn caps_xml(&self) -> String {
+        if let Some(caps) = &self.caps {
+            return caps.clone();
+        }
+
+        let params = self.supported_params.join(",");
+        let mut categories = self.releases.iter().map(|r| r.category / 1000 * 1000).collect::<Vec<_>>();
+        categories.push(5000);
+        categories.sort_unstable();
+        categories.dedup();
+
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<caps><server title=\"Mock\"/>\
+            <limits max=\"{}\" default=\"{}\"/><searching>\
+            <search available=\"yes\" supportedParams=\"{}\"/>\
+            <tv-search available=\"yes\" supportedParams=\"{},season,ep\"/>\
+            <movie-search available=\"yes\" supportedParams=\"{},imdbid\"/>\
+            <{} available=\"yes\" supportedParams=\"{},artist,album\"/>\
+            </searching><categories>",
+            self.max_limit, self.default_limit, params, params, params,
+            if self.torznab { "music-search" } else { "audio-search" }, params,
+        );
+        for id in categories {
+            let _ = write!(xml, "<category id=\"{}\" name=\"Category {}\"/>", id, id);
+        }
+        xml.push_str("</categories></caps>");
+        xml
+    }
+
+    fn search_xml(&self, params: &BTreeMap<String, String>) -> String {
+        let matching = self.releases.iter().filter(|r| r.matches(params)).collect::<Vec<_>>();
+        let offset = params.get("offset").and_then(|o| o.parse::<usize>().ok()).unwrap_or(0);
+        let limit = params.get("limit")
+            .and_then(|l| l.parse::<u32>().ok())
+            .unwrap_or(self.default_limit)
+            .min(self.max_limit) as usize;
+        let offset = if self.offset_in_pages { offset * limit } else { offset };
+
+        let response = self.reports_total.then_some((offset, matching.len()));
+        self.channel_xml(response, matching.into_iter().skip(offset).take(limit))
+    }
+
+    /// A channel with the `releases`, `response` is the `(offset, total)` of `newznab:response`
+    fn channel_xml<'a>(&self, response: Option<(usize, usize)>, releases: impl IntoIterator<Item = &'a MockRelease>) -> String {
+        let (namespace, schema) = if self.torznab {
+            ("torznab", "http://torznab.com/schemas/2015/feed")
+        } else {
+            ("newznab", "http://www.newznab.com/DTD/2010/feeds/attributes/")
+        };
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" xmlns:{}=\"{}\">\
+            <channel><title>Mock</title><link>{}</link><description>Mock indexer</description>",
+            namespace, schema, MOCK_URL,
+        );
+        if let Some((offset, total)) = response {
+            let _ = write!(xml, "<{}:response offset=\"{}\" total=\"{}\"/>", namespace, offset, total);
+        }
+        for release in releases {
+            release.write_item(&mut xml, self.api_key.as_ref(), namespace);
+        }
+        xml.push_str("</channel></rss>");
+        xml
+    }
+
+    /// The release with the guid of the `id` parameter
+    fn release(&self, params: &BTreeMap<String, String>) -> Option<&MockRelease> {
+        let id = params.get("id")?;
+        self.releases.iter().find(|r| r.id() == id)
+    }
+}
+
+fn nzb_xml(release: &MockRelease) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <nzb xmlns=\"http://www.newzbin.com/DTD/2003/nzb\"><head><meta type=\"name\">{}</meta></head>\
+        <file poster=\"mock@mock.newznab\" date=\"1717236000\" subject=\"{}\"><groups><group>alt.binaries.mock</group></groups>\
+        <segments><segment bytes=\"{}\" number=\"1\">{}@mock.newznab</segment></segments></file></nzb>",
+        escape(&release.title), escape(&release.title), release.size, escape(release.id()),
+    )
+}
+
+#[cfg_attr(target_arch = "wasm32", maybe_async::maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async::maybe_async)]
+impl Transport for MockIndexer {
+    async fn get(&self, url: &str, query: &BTreeMap<String, String>) -> Result<TransportResponse, Error> {
+        Ok(self.respond(url, query))
+    }
+}
+
+fn error_response(code: u16, description: &str) -> TransportResponse {
+    TransportResponse::ok(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<error code=\"{}\" description=\"{}\"/>",
+        code, escape(description),
+    ))
+}
+
+fn escape(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
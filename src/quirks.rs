@@ -0,0 +1,295 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::models::{Caps, RssItem, NAMESPACES};
+
+/// The indexer software behind an API, detected from the caps or from a response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerSoftware {
+    /// The original newznab
+    Newznab,
+    NzbHydra2,
+    NzEDb,
+    NNTmux,
+    Spotweb,
+    #[default]
+    Unknown,
+}
+
+impl Display for ServerSoftware {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ServerSoftware::Newznab => "newznab",
+            ServerSoftware::NzbHydra2 => "NZBHydra 2",
+            ServerSoftware::NzEDb => "nZEDb",
+            ServerSoftware::NNTmux => "NNTmux",
+            ServerSoftware::Spotweb => "Spotweb",
+            ServerSoftware::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl ServerSoftware {
+    /// Detects the software from the `<server>` title and url of the caps.
+    pub fn detect(caps: &Caps) -> Self {
+        Self::from_text(caps.server.title())
+            .or_else(|| Self::from_text(caps.server.url()))
+            .unwrap_or_default()
+    }
+
+    /// Detects the software from a search response, by the channel's title, description
+    /// and links, or by attributes only a specific software adds to its items.
+    pub fn detect_response(channel: &rss::Channel) -> Self {
+        let atom_link = channel.extensions.get("atom")
+            .and_then(|atom| atom.get("link"))
+            .and_then(|links| links.first())
+            .and_then(|link| link.attrs.get("href"))
+            .map_or("", String::as_str);
+        [channel.title.as_str(), channel.description.as_str(), channel.link.as_str(), atom_link]
+            .into_iter()
+            .find_map(Self::from_text)
+            .or_else(|| {
+                let hydra = channel.items.iter().any(|item| has_attr(item, "hydraIndexerName"));
+                hydra.then_some(ServerSoftware::NzbHydra2)
+            })
+            .unwrap_or_default()
+    }
+
+    fn from_text(text: &str) -> Option<Self> {
+        let text = text.to_lowercase();
+        if text.contains("hydra") {
+            Some(ServerSoftware::NzbHydra2)
+        } else if text.contains("nzedb") {
+            Some(ServerSoftware::NzEDb)
+        } else if text.contains("nntmux") {
+            Some(ServerSoftware::NNTmux)
+        } else if text.contains("spotweb") {
+            Some(ServerSoftware::Spotweb)
+        } else if text.contains("newznab") {
+            Some(ServerSoftware::Newznab)
+        } else {
+            None
+        }
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        match self {
+            ServerSoftware::Newznab => Quirks {
+                max_limit: Some(100),
+                ..Quirks::default()
+            },
+            ServerSoftware::NzbHydra2 => Quirks {
+                exact_total: false,
+                ..Quirks::default()
+            },
+            ServerSoftware::NzEDb | ServerSoftware::NNTmux => Quirks {
+                max_limit: Some(100),
+                attr_aliases: &[("imdb", "imdbid"), ("tvdb", "tvdbid"), ("tvmaze", "tvmazeid")],
+                ..Quirks::default()
+            },
+            ServerSoftware::Spotweb => Quirks {
+                max_limit: Some(500),
+                fallback_limit: 25,
+                offset_in_pages: true,
+                ..Quirks::default()
+            },
+            ServerSoftware::Unknown => Quirks::default(),
+        }
+    }
+}
+
+/// How a [`ServerSoftware`] deviates from the newznab specification.
+///
+/// Only XML responses are parsed, so the diverging JSON shapes of the servers (e.g. where
+/// `attr`s and the `@attributes` of an item end up) need no quirks. The values follow the
+/// documentation and sources of each software and have not been verified against live
+/// servers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quirks {
+    /// The largest `limit` the server honours, whatever its caps say
+    pub max_limit: Option<u32>,
+    /// The page size used if the caps have no `<limits>`
+    pub fallback_limit: u32,
+    /// Non-standard attribute names and their standard name, renamed while parsing
+    pub attr_aliases: &'static [(&'static str, &'static str)],
+    /// `false` if the `total` of a response is only an estimate, e.g. because a meta
+    /// indexer removes duplicates after counting
+    pub exact_total: bool,
+    /// `true` if `offset` counts pages of `limit` items instead of items
+    pub offset_in_pages: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            max_limit: None,
+            fallback_limit: 100,
+            attr_aliases: &[],
+            exact_total: true,
+            offset_in_pages: false,
+        }
+    }
+}
+
+impl Quirks {
+    /// The number of items to request per page given the caps' maximum (`0` if unknown).
+    pub fn page_size(&self, caps_max: i32) -> u32 {
+        let size = if caps_max > 0 { caps_max as u32 } else { self.fallback_limit };
+        self.max_limit.map_or(size, |max| size.min(max))
+    }
+
+    /// The `offset` to send for a page starting at item `offset`, servers counting pages
+    /// start at the page containing it.
+    pub fn request_offset(&self, offset: u64, limit: u32) -> u64 {
+        if self.offset_in_pages && limit > 0 { offset / limit as u64 } else { offset }
+    }
+
+    /// The item the page requested for `offset` starts at.
+    pub fn page_start(&self, offset: u64, limit: u32) -> u64 {
+        if self.offset_in_pages { self.request_offset(offset, limit) * limit as u64 } else { offset }
+    }
+
+    /// Renames the aliased attributes of `items` to their standard name, unless an item
+    /// already has the standard attribute.
+    pub fn normalize(&self, items: &mut [RssItem]) {
+        if self.attr_aliases.is_empty() {
+            return;
+        }
+
+        for item in items {
+            for (alias, standard) in self.attr_aliases {
+                if has_attr(item, standard) {
+                    continue;
+                }
+                let attrs = item.extensions.iter_mut()
+                    .filter(|(ns, _)| NAMESPACES.contains(&ns.as_str()))
+                    .filter_map(|(_, namespace)| namespace.get_mut("attr"))
+                    .flatten();
+                for attr in attrs {
+                    if attr.attrs.get("name").map(String::as_str) == Some(alias) {
+                        attr.attrs.insert("name".to_string(), standard.to_string());
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn has_attr(item: &RssItem, name: &str) -> bool {
+    NAMESPACES.iter()
+        .filter_map(|ns| item.extensions.get(*ns))
+        .filter_map(|namespace| namespace.get("attr"))
+        .flatten()
+        .any(|attr| attr.attrs.get("name").map(String::as_str) == Some(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::common::models::{Caps, Release, SearchQuery};
+    use crate::mock::{MockIndexer, MockRelease};
+    use crate::quirks::ServerSoftware;
+
+    fn caps(server: &str) -> Caps {
+        Caps::try_from(format!("<caps>{}<searching/><categories/></caps>", server)).unwrap()
+    }
+
+    fn channel(head: &str, items: &str) -> rss::Channel {
+        rss::Channel::from_str(&format!(
+            "<rss version=\"2.0\" xmlns:newznab=\"http://www.newznab.com/DTD/2010/feeds/attributes/\">\
+            <channel>{}{}</channel></rss>",
+            head, items,
+        )).unwrap()
+    }
+
+    #[test]
+    fn detects_software() {
+        let expected = [
+            (r#"<server title="NZBHydra 2" url="https://github.com/theotherp/nzbhydra2"/>"#, ServerSoftware::NzbHydra2),
+            (r#"<server title="nZEDb"/>"#, ServerSoftware::NzEDb),
+            (r#"<server title="A great usenet indexer" url="https://nntmux.example"/>"#, ServerSoftware::NNTmux),
+            (r#"<server title="Spotweb"/>"#, ServerSoftware::Spotweb),
+            (r#"<server title="My Indexer"/>"#, ServerSoftware::Unknown),
+        ];
+        for (server, software) in expected {
+            assert_eq!(ServerSoftware::detect(&caps(server)), software, "{}", server);
+        }
+
+        let by_title = channel("<title>nZEDb</title><link/><description>nZEDb API Results</description>", "");
+        assert_eq!(ServerSoftware::detect_response(&by_title), ServerSoftware::NzEDb);
+        let by_attr = channel(
+            "<title>Results</title><link/><description/>",
+            r#"<item><title>A</title><newznab:attr name="hydraIndexerName" value="idx"/></item>"#,
+        );
+        assert_eq!(ServerSoftware::detect_response(&by_attr), ServerSoftware::NzbHydra2);
+    }
+
+    #[test]
+    fn applies_quirks() {
+        let quirks = ServerSoftware::NzEDb.quirks();
+        assert_eq!(quirks.page_size(0), 100);
+        assert_eq!(quirks.page_size(500), 100);
+        assert_eq!(ServerSoftware::Spotweb.quirks().page_size(0), 25);
+        assert_eq!(ServerSoftware::Unknown.quirks().page_size(250), 250);
+
+        let mut channel = channel(
+            "<title>nZEDb</title><link/><description/>",
+            r#"<item><title>Movie</title><newznab:attr name="imdb" value="0133093"/></item>
+            <item><title>Both</title><newznab:attr name="imdb" value="1"/><newznab:attr name="imdbid" value="2"/></item>"#,
+        );
+        assert_eq!(Release::from_item_ref(&channel.items[0]).attr("imdbid"), None);
+        quirks.normalize(&mut channel.items);
+        assert_eq!(Release::from_item_ref(&channel.items[0]).attr("imdbid"), Some("0133093"));
+        assert_eq!(Release::from_item_ref(&channel.items[1]).attr("imdbid"), Some("2"));
+    }
+
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn client_applies_quirks() {
+        let indexer = MockIndexer::new().limits(500, 100);
+        let client = indexer.client_builder().server(ServerSoftware::Newznab).to_client();
+        client.search(SearchQuery::new("show").into()).await.unwrap();
+        client.search(SearchQuery::new("show").limit(1000).into()).await.unwrap();
+        let limits = indexer.requests().iter()
+            .filter_map(|r| r.params.get("limit").cloned())
+            .collect::<Vec<_>>();
+        assert_eq!(limits, vec!["100", "100"]);
+
+        let without_limits = MockIndexer::new()
+            .caps(r#"<caps><server title="nZEDb"/><searching><search available="yes"/></searching><categories/></caps>"#)
+            .client();
+        assert_eq!(without_limits.get_server(), ServerSoftware::NzEDb);
+        assert_eq!(without_limits.page_size(), 100);
+    }
+
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn pages_by_page_number() {
+        let quirks = ServerSoftware::Spotweb.quirks();
+        assert_eq!((quirks.request_offset(20, 10), quirks.page_start(25, 10)), (2, 20));
+        assert_eq!(ServerSoftware::Newznab.quirks().request_offset(20, 10), 20);
+
+        let indexer = MockIndexer::new()
+            .limits(10, 10)
+            .offset_in_pages()
+            .releases((0..25).map(|i| MockRelease::new(format!("Show.S01E{:02}", i))));
+        let client = indexer.client_builder().server(ServerSoftware::Spotweb).to_client();
+        let mut result = client.search(SearchQuery::new("show").into()).await.unwrap();
+        result.all(&client).await.unwrap();
+
+        let titles = result.items.iter().map(|i| i.title().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(titles, (0..25).map(|i| format!("Show.S01E{:02}", i)).collect::<Vec<_>>());
+        let offsets = indexer.requests().iter()
+            .filter_map(|r| r.params.get("offset").cloned())
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, vec!["1", "2"]);
+    }
+}
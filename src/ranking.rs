@@ -0,0 +1,299 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::common::models::Release;
+use crate::release_name::{Codec, ReleaseName, Resolution, Source};
+
+const MB: f64 = 1024.0 * 1024.0;
+
+/// The criteria a [`ScoringProfile`] scores releases by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Criterion {
+    Resolution,
+    Source,
+    Codec,
+    Group,
+    SizePerMinute,
+    Age,
+    Grabs,
+}
+
+/// How much a single criterion contributed to the score of a release
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreComponent {
+    pub criterion: Criterion,
+    /// Weighted score, negative for penalties
+    pub score: f64,
+    /// Human readable reason, e.g. `1080p` or `12.3 MB/min`
+    pub reason: String,
+}
+
+/// A release together with its score and how the score came about
+#[derive(Debug, Clone)]
+pub struct RankedRelease {
+    pub release: Release,
+    pub score: f64,
+    pub breakdown: Vec<ScoreComponent>,
+}
+
+impl Display for RankedRelease {
+    /// Formats as `<score> <title> [<criterion> <score> (<reason>), ...]`, meant for logs.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.2} {}", self.score, self.release.title().unwrap_or("<untitled>"))?;
+        let parts = self.breakdown.iter()
+            .map(|c| format!("{:?} {:+.2} ({})", c.criterion, c.score, c.reason))
+            .collect::<Vec<_>>();
+        write!(f, " [{}]", parts.join(", "))
+    }
+}
+
+/// Weight of every criterion, the score of a criterion ranges from `0` to its weight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weights {
+    pub resolution: f64,
+    pub source: f64,
+    pub codec: f64,
+    pub group: f64,
+    pub size_per_minute: f64,
+    pub age: f64,
+    pub grabs: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            resolution: 4.0,
+            source: 3.0,
+            codec: 1.0,
+            group: 2.0,
+            size_per_minute: 2.0,
+            age: 1.0,
+            grabs: 1.0,
+        }
+    }
+}
+
+/// Configures how releases are ranked against each other.
+///
+/// Preference lists are ordered from most to least preferred, values that are not listed
+/// score zero.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ScoringProfile {
+    resolutions: Vec<Resolution>,
+    sources: Vec<Source>,
+    codecs: Vec<Codec>,
+    preferred_groups: Vec<String>,
+    avoided_groups: Vec<String>,
+    /// ideal size in MB per minute of runtime
+    size_per_minute: Option<(f64, f64)>,
+    runtime: Option<Duration>,
+    age_half_life: Option<Duration>,
+    weights: Weights,
+}
+
+impl ScoringProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn resolutions(mut self, value: impl IntoIterator<Item = Resolution>) -> Self {
+        self.resolutions = value.into_iter().collect();
+        self
+    }
+    pub fn sources(mut self, value: impl IntoIterator<Item = Source>) -> Self {
+        self.sources = value.into_iter().collect();
+        self
+    }
+    pub fn codecs(mut self, value: impl IntoIterator<Item = Codec>) -> Self {
+        self.codecs = value.into_iter().collect();
+        self
+    }
+    pub fn preferred_groups(mut self, value: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.preferred_groups = value.into_iter().map(|g| g.as_ref().to_lowercase()).collect();
+        self
+    }
+    /// Groups whose releases are penalized by the group weight
+    pub fn avoided_groups(mut self, value: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.avoided_groups = value.into_iter().map(|g| g.as_ref().to_lowercase()).collect();
+        self
+    }
+    /// The ideal range of megabytes per minute of runtime, only used if a
+    /// [`runtime`](Self::runtime) is set
+    pub fn size_per_minute(mut self, min_mb: f64, max_mb: f64) -> Self {
+        self.size_per_minute = Some((min_mb.min(max_mb), max_mb.max(min_mb)));
+        self
+    }
+    /// Runtime of the searched movie or episode
+    pub fn runtime(mut self, value: Duration) -> Self {
+        self.runtime = Some(value);
+        self
+    }
+    /// Favours newer releases, a release loses half of the age score every `value`
+    pub fn age_half_life(mut self, value: Duration) -> Self {
+        self.age_half_life = Some(value);
+        self
+    }
+    pub fn weights(mut self, value: Weights) -> Self {
+        self.weights = value;
+        self
+    }
+
+    /// Scores all releases and returns them best first, releases with equal scores keep
+    /// their original order.
+    pub fn rank(&self, releases: impl IntoIterator<Item = Release>) -> Vec<RankedRelease> {
+        self.rank_at(releases, Utc::now())
+    }
+
+    /// Like [`rank`](Self::rank) with ages calculated relative to `now`.
+    pub fn rank_at(&self, releases: impl IntoIterator<Item = Release>, now: DateTime<Utc>) -> Vec<RankedRelease> {
+        let releases = releases.into_iter().collect::<Vec<_>>();
+        let max_grabs = releases.iter().filter_map(Release::grabs).max().unwrap_or(0);
+
+        let mut ranked = releases.into_iter()
+            .map(|release| {
+                let breakdown = self.score(&release, max_grabs, now);
+                RankedRelease {
+                    score: breakdown.iter().map(|c| c.score).sum(),
+                    release,
+                    breakdown,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+        ranked
+    }
+
+    /// The best release, if any
+    pub fn best(&self, releases: impl IntoIterator<Item = Release>) -> Option<RankedRelease> {
+        self.rank(releases).into_iter().next()
+    }
+
+    fn score(&self, release: &Release, max_grabs: u32, now: DateTime<Utc>) -> Vec<ScoreComponent> {
+        let name = release.parsed_name().unwrap_or_default();
+        let weights = &self.weights;
+        let mut breakdown = vec![];
+
+        if let Some(resolution) = name.resolution {
+            let score = preference(&self.resolutions, &resolution) * weights.resolution;
+            breakdown.push(component(Criterion::Resolution, score, resolution.to_string()));
+        }
+        if let Some(source) = name.source {
+            let score = preference(&self.sources, &source) * weights.source;
+            breakdown.push(component(Criterion::Source, score, format!("{:?}", source)));
+        }
+        if let Some(codec) = name.codec {
+            let score = preference(&self.codecs, &codec) * weights.codec;
+            breakdown.push(component(Criterion::Codec, score, format!("{:?}", codec)));
+        }
+        if let Some(component) = self.score_group(&name) {
+            breakdown.push(component);
+        }
+        if let (Some((min, max)), Some(runtime), Some(size)) = (self.size_per_minute, self.runtime, release.size()) {
+            let minutes = runtime.as_secs_f64() / 60.0;
+            if minutes > 0.0 {
+                let per_minute = size as f64 / MB / minutes;
+                let score = range_score(per_minute, min, max) * weights.size_per_minute;
+                breakdown.push(component(Criterion::SizePerMinute, score, format!("{:.1} MB/min", per_minute)));
+            }
+        }
+        if let (Some(half_life), Some(age)) = (self.age_half_life, release.age(now)) {
+            let score = 0.5f64.powf(age.as_secs_f64() / half_life.as_secs_f64().max(1.0)) * weights.age;
+            breakdown.push(component(Criterion::Age, score, format!("{:.1} days", age.as_secs_f64() / 86400.0)));
+        }
+        if let Some(grabs) = release.grabs().filter(|_| max_grabs > 0) {
+            let score = ((grabs as f64 + 1.0).ln() / (max_grabs as f64 + 1.0).ln()) * weights.grabs;
+            breakdown.push(component(Criterion::Grabs, score, format!("{} grabs", grabs)));
+        }
+
+        breakdown
+    }
+
+    fn score_group(&self, name: &ReleaseName) -> Option<ScoreComponent> {
+        let group = name.group.as_ref()?;
+        let lower = group.to_lowercase();
+
+        if self.avoided_groups.contains(&lower) {
+            Some(component(Criterion::Group, -self.weights.group, format!("{} avoided", group)))
+        } else if self.preferred_groups.contains(&lower) {
+            let score = preference(&self.preferred_groups, &lower) * self.weights.group;
+            Some(component(Criterion::Group, score, group.clone()))
+        } else {
+            None
+        }
+    }
+}
+
+fn component(criterion: Criterion, score: f64, reason: String) -> ScoreComponent {
+    ScoreComponent { criterion, score, reason }
+}
+
+/// `1.0` for the first entry of `list` down to `1 / len` for the last, `0.0` if missing
+fn preference<T: PartialEq>(list: &[T], value: &T) -> f64 {
+    match list.iter().position(|v| v == value) {
+        Some(idx) => (list.len() - idx) as f64 / list.len() as f64,
+        None => 0.0,
+    }
+}
+
+/// `1.0` within `min..=max`, falling linearly to `0.0` at half the minimum or double the maximum
+fn range_score(value: f64, min: f64, max: f64) -> f64 {
+    if value < min {
+        ((value - min / 2.0) / (min / 2.0)).clamp(0.0, 1.0)
+    } else if value > max {
+        ((2.0 * max - value) / max).clamp(0.0, 1.0)
+    } else {
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::common::models::Release;
+    use crate::mock::MockRelease;
+    use crate::ranking::{Criterion, ScoringProfile};
+    use crate::release_name::{Resolution, Source};
+
+    fn release(title: &str, size: u64, grabs: u32) -> Release {
+        MockRelease::new(title).size(size).attr("grabs", grabs.to_string()).to_release()
+    }
+
+    #[test]
+    fn ranks_by_profile() {
+        let gb = 1024 * 1024 * 1024;
+        let profile = ScoringProfile::new()
+            .resolutions([Resolution::R1080p, Resolution::R720p])
+            .sources([Source::BluRay, Source::WebDl])
+            .avoided_groups(["BAD"])
+            .size_per_minute(10.0, 30.0)
+            .runtime(Duration::from_secs(100 * 60))
+            .age_half_life(Duration::from_secs(7 * 86400));
+        let now = Utc.with_ymd_and_hms(2024, 6, 8, 10, 0, 0).unwrap();
+
+        let ranked = profile.rank_at(vec![
+            release("Movie.2024.720p.WEB-DL.x264-GRP", 2 * gb, 5),
+            release("Movie.2024.1080p.BluRay.x264-BAD", 2 * gb, 50),
+            release("Movie.2024.1080p.BluRay.x264-GRP", 2 * gb, 10),
+        ], now);
+
+        let titles = ranked.iter().map(|r| r.release.title().unwrap()).collect::<Vec<_>>();
+        assert_eq!(titles, vec![
+            "Movie.2024.1080p.BluRay.x264-GRP",
+            "Movie.2024.1080p.BluRay.x264-BAD",
+            "Movie.2024.720p.WEB-DL.x264-GRP",
+        ]);
+
+        let best = &ranked[0];
+        let resolution = best.breakdown.iter().find(|c| c.criterion == Criterion::Resolution).unwrap();
+        assert_eq!(resolution.score, 4.0);
+        let age = best.breakdown.iter().find(|c| c.criterion == Criterion::Age).unwrap();
+        assert!((age.score - 0.5).abs() < 1e-9);
+        assert!(best.to_string().starts_with(&format!("{:.2} Movie.2024.1080p.BluRay.x264-GRP [", best.score)));
+    }
+}
@@ -0,0 +1,245 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::Error;
+use crate::retry;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The limits an indexer enforces, e.g. "1 request/second" and "100 API hits/day".
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimit {
+    requests: u32,
+    interval: Duration,
+    daily_cap: Option<u32>,
+}
+
+impl RateLimit {
+    /// Allows `requests` per `interval`, short bursts up to `requests` are allowed.
+    pub fn new(requests: u32, interval: Duration) -> Self {
+        Self {
+            requests: requests.max(1),
+            interval,
+            daily_cap: None,
+        }
+    }
+
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+
+    /// Limits the amount of requests within fixed 24 hour windows, the first one starts
+    /// when the [`RateLimiter`] is created. The whole budget is restored at the end of a
+    /// window, not request by request.
+    pub fn daily_cap(mut self, value: u32) -> Self {
+        self.daily_cap = Some(value);
+        self
+    }
+
+    pub fn get_requests(&self) -> u32 {
+        self.requests
+    }
+    pub fn get_interval(&self) -> Duration {
+        self.interval
+    }
+    pub fn get_daily_cap(&self) -> Option<u32> {
+        self.daily_cap
+    }
+}
+
+/// The remaining budget of a [`RateLimiter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitStatus {
+    /// Requests that can be sent right now without waiting
+    pub available: u32,
+    /// Time until the next token is added to the bucket, zero if the bucket is full
+    pub next_token_in: Duration,
+    /// Requests left for the current day, `None` if there is no daily cap
+    pub daily_remaining: Option<u32>,
+    /// Time until the daily budget is reset, `None` if there is no daily cap
+    pub daily_resets_in: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct State {
+    tokens: f64,
+    last_refill: Instant,
+    day_started: Instant,
+    used_today: u32,
+}
+
+/// A token-bucket rate limiter that every request of a [`Client`](crate::Client) waits on.
+///
+/// Clones share their state, so handing the same limiter to several clients of one indexer
+/// makes them share its budget.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    state: Arc<Mutex<State>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        let now = Instant::now();
+        Self {
+            state: Arc::new(Mutex::new(State {
+                tokens: limit.requests as f64,
+                last_refill: now,
+                day_started: now,
+                used_today: 0,
+            })),
+            limit,
+        }
+    }
+
+    pub fn get_limit(&self) -> &RateLimit {
+        &self.limit
+    }
+
+    /// Returns `true` if both limiters share the same budget.
+    pub fn shares_state_with(&self, other: &RateLimiter) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+
+    /// Waits until a request may be sent and takes a token for it.
+    ///
+    /// Fails with [`Error::RateLimited`] instead of waiting if the daily cap is exhausted.
+    #[maybe_async::maybe_async]
+    pub async fn acquire(&self) -> Result<(), Error> {
+        loop {
+            let wait = self.try_acquire_at(Instant::now())?;
+            match wait {
+                None => return Ok(()),
+                Some(delay) => {
+                    log::debug!("Rate limit reached, waiting {:?}", delay);
+                    retry::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    /// Takes a token if one is available, otherwise returns how long to wait for the next one.
+    fn try_acquire_at(&self, now: Instant) -> Result<Option<Duration>, Error> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        self.refill(&mut state, now);
+
+        if let Some(cap) = self.limit.daily_cap {
+            if state.used_today >= cap {
+                return Err(Error::RateLimited(DAY.saturating_sub(now.saturating_duration_since(state.day_started))));
+            }
+        }
+
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            state.used_today += 1;
+            Ok(None)
+        } else {
+            Ok(Some(self.time_per_token().mul_f64(1.0 - state.tokens)))
+        }
+    }
+
+    /// The current budget.
+    pub fn status(&self) -> RateLimitStatus {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        self.refill(&mut state, now);
+
+        let next_token_in = if state.tokens >= self.limit.requests as f64 {
+            Duration::ZERO
+        } else {
+            self.time_per_token().mul_f64(1.0 - state.tokens.fract())
+        };
+
+        RateLimitStatus {
+            available: match self.limit.daily_cap {
+                Some(cap) => (state.tokens as u32).min(cap.saturating_sub(state.used_today)),
+                None => state.tokens as u32,
+            },
+            next_token_in,
+            daily_remaining: self.limit.daily_cap.map(|cap| cap.saturating_sub(state.used_today)),
+            daily_resets_in: self.limit.daily_cap.map(|_| DAY.saturating_sub(now.saturating_duration_since(state.day_started))),
+        }
+    }
+
+    fn time_per_token(&self) -> Duration {
+        self.limit.interval / self.limit.requests
+    }
+
+    fn refill(&self, state: &mut State, now: Instant) {
+        let days = now.saturating_duration_since(state.day_started).as_secs() / DAY.as_secs();
+        if days > 0 {
+            // windows stay aligned to the first one instead of restarting at this request
+            state.day_started += DAY * days as u32;
+            state.used_today = 0;
+        }
+
+        let elapsed = now.saturating_duration_since(state.last_refill);
+        let per_token = self.time_per_token().as_secs_f64();
+        let new_tokens = if per_token > 0.0 { elapsed.as_secs_f64() / per_token } else { f64::MAX };
+        state.tokens = (state.tokens + new_tokens).min(self.limit.requests as f64);
+        state.last_refill = now;
+    }
+}
+
+impl From<RateLimit> for RateLimiter {
+    fn from(limit: RateLimit) -> Self {
+        Self::new(limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::Error;
+    use crate::rate_limit::{RateLimit, RateLimiter};
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let limiter = RateLimiter::new(RateLimit::new(2, Duration::from_secs(2)));
+        let start = Instant::now();
+
+        assert_eq!(limiter.try_acquire_at(start).unwrap(), None);
+        assert_eq!(limiter.try_acquire_at(start).unwrap(), None);
+        let wait = limiter.try_acquire_at(start).unwrap().unwrap();
+        assert!(wait <= Duration::from_secs(1) && wait > Duration::from_millis(900));
+
+        assert_eq!(limiter.try_acquire_at(start + Duration::from_secs(1)).unwrap(), None);
+    }
+
+    #[test]
+    fn daily_cap_is_enforced_and_shared() {
+        let limiter = RateLimiter::new(RateLimit::new(10, Duration::from_secs(1)).daily_cap(2));
+        let shared = limiter.clone();
+        let now = Instant::now();
+
+        assert!(limiter.try_acquire_at(now).is_ok());
+        assert!(shared.try_acquire_at(now).is_ok());
+        assert!(matches!(limiter.try_acquire_at(now), Err(Error::RateLimited(_))));
+
+        let status = shared.status();
+        assert_eq!(status.daily_remaining, Some(0));
+        assert_eq!(status.available, 0);
+        assert!(limiter.shares_state_with(&shared));
+    }
+
+    #[test]
+    fn daily_cap_resets_at_window_end() {
+        let limiter = RateLimiter::new(RateLimit::new(10, Duration::from_secs(1)).daily_cap(1));
+        let start = limiter.state.lock().unwrap().day_started;
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        assert!(limiter.try_acquire_at(start + Duration::from_secs(3600)).is_ok());
+        let just_before = limiter.try_acquire_at(start + day - Duration::from_millis(1));
+        assert!(matches!(just_before, Err(Error::RateLimited(wait)) if wait <= Duration::from_millis(1)));
+
+        // the second window starts a day after the first, not after the last request
+        assert!(limiter.try_acquire_at(start + day).is_ok());
+        assert!(limiter.try_acquire_at(start + 2 * day - Duration::from_millis(1)).is_err());
+        assert!(limiter.try_acquire_at(start + 2 * day + Duration::from_secs(3600)).is_ok());
+    }
+}
@@ -0,0 +1,397 @@
+use std::fmt::Display;
+
+use chrono::NaiveDate;
+
+/// Video resolution of a release
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Resolution {
+    R480p,
+    R576p,
+    R720p,
+    R1080p,
+    R2160p,
+}
+
+/// Where a release was captured or ripped from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Source {
+    Cam,
+    Telesync,
+    Dvd,
+    Tv,
+    Web,
+    WebDl,
+    WebRip,
+    BluRay,
+    Remux,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Codec {
+    Xvid,
+    Mpeg2,
+    H264,
+    H265,
+    Vp9,
+    Av1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Audio {
+    Mp3,
+    Aac,
+    Ac3,
+    Eac3,
+    Dts,
+    DtsHd,
+    TrueHd,
+    Atmos,
+    Flac,
+    Opus,
+}
+
+/// The information encoded in a scene-style release name such as
+/// `Show.Name.S01E02.1080p.WEB.h264-GROUP`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReleaseName {
+    /// Show or movie title with separators replaced by spaces
+    pub title: String,
+    pub year: Option<u16>,
+    pub season: Option<u32>,
+    /// Episode numbers, more than one for multi-episode releases, empty for season packs
+    pub episodes: Vec<u32>,
+    /// Air date of daily shows
+    pub air_date: Option<NaiveDate>,
+    pub resolution: Option<Resolution>,
+    pub source: Option<Source>,
+    pub codec: Option<Codec>,
+    pub audio: Vec<Audio>,
+    /// Lowercased language tags, e.g. `german`, `multi` or `dl`
+    pub languages: Vec<String>,
+    pub proper: bool,
+    pub repack: bool,
+    pub group: Option<String>,
+}
+
+const LANGUAGES: [&str; 20] = [
+    "english", "german", "french", "spanish", "italian", "dutch", "portuguese", "russian", "polish",
+    "swedish", "danish", "norwegian", "finnish", "japanese", "korean", "chinese", "hindi", "turkish",
+    "multi", "dl",
+];
+
+/// Tokens of the last dash-separated part that are no release group, e.g. in `WEB-DL`
+const NO_GROUP: [&str; 6] = ["dl", "hd", "ray", "rip", "ma", "x"];
+
+impl ReleaseName {
+    /// Parses a release name, fields that could not be recognized are left empty.
+    pub fn parse(name: &str) -> Self {
+        let mut parsed = ReleaseName::default();
+
+        let (rest, group) = split_group(name.trim());
+        parsed.group = group;
+
+        let tokens = rest
+            .split(['.', '_', ' ', '-', '(', ')', '[', ']', '{', '}'])
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>();
+        let lower = tokens.iter().map(|t| t.to_lowercase()).collect::<Vec<_>>();
+
+        // index of the first token that is no longer part of the title
+        let mut title_end = None;
+        let mut i = 0;
+        while i < lower.len() {
+            let token = lower[i].as_str();
+            let next = lower.get(i + 1).map(String::as_str);
+            let mut consumed = 1;
+            let mut marker = true;
+
+            if let Some(date) = parse_date(&lower[i..]) {
+                parsed.air_date = Some(date);
+                consumed = 3;
+            } else if let Some(year) = parse_year(token).filter(|_| i > 0) {
+                // a year directly followed by another one belongs to the title, e.g. `2012.2009`
+                if next.and_then(parse_year).is_some() {
+                    marker = false;
+                } else {
+                    parsed.year = Some(year);
+                }
+            } else if let Some((season, episodes)) = parse_episode(token) {
+                parsed.season = Some(season);
+                parsed.episodes.extend(episodes);
+                while let Some(more) = lower.get(i + consumed).and_then(|t| parse_continued_episode(t)) {
+                    parsed.episodes.push(more);
+                    consumed += 1;
+                }
+            } else if let Some(resolution) = parse_resolution(token) {
+                parsed.resolution = Some(resolution);
+            } else if let Some((source, n)) = parse_source(token, next) {
+                parsed.source = match (parsed.source, source) {
+                    (Some(Source::BluRay), Source::Remux) | (None, _) => Some(source),
+                    (current, _) => current,
+                };
+                consumed = n;
+            } else if let Some((codec, n)) = parse_codec(token, next) {
+                parsed.codec = Some(codec);
+                consumed = n;
+            } else if let Some((audio, n)) = parse_audio(token, next) {
+                if !parsed.audio.contains(&audio) {
+                    parsed.audio.push(audio);
+                }
+                consumed = n;
+            } else if LANGUAGES.contains(&token) && i > 0 {
+                parsed.languages.push(token.to_string());
+            } else if token == "proper" || token == "rerip" {
+                parsed.proper = true;
+            } else if token == "repack" {
+                parsed.repack = true;
+            } else {
+                marker = false;
+            }
+
+            if marker && title_end.is_none() {
+                title_end = Some(i);
+            }
+            i += consumed;
+        }
+
+        parsed.title = tokens[..title_end.unwrap_or(tokens.len())].join(" ");
+        parsed
+    }
+
+    pub fn is_episode(&self) -> bool {
+        !self.episodes.is_empty() || self.air_date.is_some()
+    }
+
+    pub fn is_season_pack(&self) -> bool {
+        self.season.is_some() && self.episodes.is_empty()
+    }
+}
+
+impl From<&str> for ReleaseName {
+    fn from(value: &str) -> Self {
+        Self::parse(value)
+    }
+}
+
+/// Splits off the release group after the last dash, e.g. `-GROUP` or `-GROUP[tag]`.
+fn split_group(name: &str) -> (&str, Option<String>) {
+    let Some((rest, group)) = name.rsplit_once('-') else {
+        return (name, None);
+    };
+
+    // indexers append tags like `[eztv]`, anything else with spaces is part of the title,
+    // e.g. `X-Men 2000 720p`
+    let group = group.split('[').next().unwrap_or_default().trim_end();
+    let valid = !group.is_empty()
+        && !group.contains(char::is_whitespace)
+        && !group.contains('.')
+        && group.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !NO_GROUP.contains(&group.to_lowercase().as_str())
+        && parse_episode(&group.to_lowercase()).is_none()
+        && parse_continued_episode(&group.to_lowercase()).is_none()
+        && parse_year(group).is_none();
+
+    if valid {
+        (rest, Some(group.to_string()))
+    } else {
+        (name, None)
+    }
+}
+
+fn parse_year(token: &str) -> Option<u16> {
+    if token.len() != 4 {
+        return None;
+    }
+    token.parse::<u16>().ok().filter(|y| (1900..=2099).contains(y))
+}
+
+fn parse_date(tokens: &[String]) -> Option<NaiveDate> {
+    let [year, month, day, ..] = tokens else { return None };
+    if month.len() != 2 || day.len() != 2 {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(parse_year(year)? as i32, month.parse().ok()?, day.parse().ok()?)
+}
+
+/// `s01e02`, `s01e02e03`, `s01` or `1x02`
+fn parse_episode(token: &str) -> Option<(u32, Vec<u32>)> {
+    if let Some((season, episode)) = token.split_once('x') {
+        if (1..=2).contains(&season.len()) && (2..=3).contains(&episode.len()) {
+            return Some((season.parse().ok()?, vec![episode.parse().ok()?]));
+        }
+        return None;
+    }
+
+    let rest = token.strip_prefix('s')?;
+    let mut parts = rest.split('e');
+    let season = parts.next()?;
+    if season.is_empty() || season.len() > 3 || !season.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let episodes = parts
+        .map(|e| if e.is_empty() || e.len() > 4 { None } else { e.parse::<u32>().ok() })
+        .collect::<Option<Vec<_>>>()?;
+    Some((season.parse().ok()?, episodes))
+}
+
+/// Additional episodes of a multi-episode release, e.g. the `e03` of `s01e02-e03`
+fn parse_continued_episode(token: &str) -> Option<u32> {
+    let e = token.strip_prefix('e')?;
+    if e.is_empty() || e.len() > 3 {
+        return None;
+    }
+    e.parse().ok()
+}
+
+fn parse_resolution(token: &str) -> Option<Resolution> {
+    match token {
+        "480p" | "480i" => Some(Resolution::R480p),
+        "576p" | "576i" => Some(Resolution::R576p),
+        "720p" => Some(Resolution::R720p),
+        "1080p" | "1080i" => Some(Resolution::R1080p),
+        "2160p" | "4k" | "uhd" => Some(Resolution::R2160p),
+        _ => None,
+    }
+}
+
+fn parse_source(token: &str, next: Option<&str>) -> Option<(Source, usize)> {
+    let source = match (token, next) {
+        ("web", Some("dl")) => return Some((Source::WebDl, 2)),
+        ("web", Some("rip")) => return Some((Source::WebRip, 2)),
+        ("blu", Some("ray")) => return Some((Source::BluRay, 2)),
+        ("web", _) => Source::Web,
+        ("webdl", _) => Source::WebDl,
+        ("webrip", _) => Source::WebRip,
+        ("bluray" | "bdrip" | "brrip" | "bd", _) => Source::BluRay,
+        ("remux", _) => Source::Remux,
+        ("hdtv" | "pdtv" | "sdtv" | "dsr", _) => Source::Tv,
+        ("dvdrip" | "dvd" | "dvdr", _) => Source::Dvd,
+        ("cam" | "hdcam", _) => Source::Cam,
+        ("ts" | "telesync" | "hdts", _) => Source::Telesync,
+        _ => return None,
+    };
+    Some((source, 1))
+}
+
+fn parse_codec(token: &str, next: Option<&str>) -> Option<(Codec, usize)> {
+    let codec = match (token, next) {
+        ("h", Some("264")) => return Some((Codec::H264, 2)),
+        ("h", Some("265")) => return Some((Codec::H265, 2)),
+        ("x264" | "h264" | "avc", _) => Codec::H264,
+        ("x265" | "h265" | "hevc", _) => Codec::H265,
+        ("xvid" | "divx", _) => Codec::Xvid,
+        ("mpeg2", _) => Codec::Mpeg2,
+        ("vp9", _) => Codec::Vp9,
+        ("av1", _) => Codec::Av1,
+        _ => return None,
+    };
+    Some((codec, 1))
+}
+
+fn parse_audio(token: &str, next: Option<&str>) -> Option<(Audio, usize)> {
+    // channel layouts like `5.1` are split into two tokens, e.g. `dd5` and `1`
+    let trimmed = token.trim_end_matches(|c: char| c.is_ascii_digit());
+    let consumed = if trimmed.len() != token.len() && next.is_some_and(|n| n.len() == 1 && n.bytes().all(|b| b.is_ascii_digit())) {
+        2
+    } else {
+        1
+    };
+
+    let audio = match (trimmed, next) {
+        ("dts", Some("hd")) => return Some((Audio::DtsHd, 2)),
+        ("dts", _) => Audio::Dts,
+        ("dtshd" | "dtsma", _) => Audio::DtsHd,
+        ("ddp" | "eac" | "dd+", _) => Audio::Eac3,
+        ("dd" | "ac", _) if token != "ac" => Audio::Ac3,
+        ("aac", _) => Audio::Aac,
+        ("truehd", _) => Audio::TrueHd,
+        ("atmos", _) => Audio::Atmos,
+        ("flac", _) => Audio::Flac,
+        ("mp", _) if token == "mp3" => Audio::Mp3,
+        ("opus", _) => Audio::Opus,
+        _ => return None,
+    };
+    Some((audio, consumed))
+}
+
+impl Display for Resolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let repr = match self {
+            Resolution::R480p => "480p",
+            Resolution::R576p => "576p",
+            Resolution::R720p => "720p",
+            Resolution::R1080p => "1080p",
+            Resolution::R2160p => "2160p",
+        };
+        write!(f, "{}", repr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::release_name::{Audio, Codec, ReleaseName, Resolution, Source};
+
+    #[test]
+    fn parse_episode_release() {
+        let parsed = ReleaseName::parse("Show.Name.S01E02.1080p.WEB.h264-GROUP");
+
+        assert_eq!(parsed.title, "Show Name");
+        assert_eq!(parsed.season, Some(1));
+        assert_eq!(parsed.episodes, vec![2]);
+        assert_eq!(parsed.resolution, Some(Resolution::R1080p));
+        assert_eq!(parsed.source, Some(Source::Web));
+        assert_eq!(parsed.codec, Some(Codec::H264));
+        assert_eq!(parsed.group.as_deref(), Some("GROUP"));
+        assert!(parsed.is_episode());
+    }
+
+    #[test]
+    fn parse_multi_episode_and_tags() {
+        let parsed = ReleaseName::parse("Show.Name.2019.S02E03-E04.German.DL.720p.WEB-DL.DDP5.1.H.265.REPACK-GRP");
+
+        assert_eq!(parsed.title, "Show Name");
+        assert_eq!(parsed.year, Some(2019));
+        assert_eq!(parsed.episodes, vec![3, 4]);
+        assert_eq!(parsed.languages, vec!["german".to_string(), "dl".to_string()]);
+        assert_eq!(parsed.source, Some(Source::WebDl));
+        assert_eq!(parsed.audio, vec![Audio::Eac3]);
+        assert_eq!(parsed.codec, Some(Codec::H265));
+        assert!(parsed.repack);
+        assert_eq!(parsed.group.as_deref(), Some("GRP"));
+    }
+
+    #[test]
+    fn parse_movie_and_daily() {
+        let movie = ReleaseName::parse("2012.2009.PROPER.2160p.UHD.BluRay.REMUX.TrueHD.Atmos.7.1.HEVC-Team");
+        assert_eq!(movie.title, "2012");
+        assert_eq!(movie.year, Some(2009));
+        assert!(movie.proper);
+        assert_eq!(movie.source, Some(Source::Remux));
+        assert_eq!(movie.audio, vec![Audio::TrueHd, Audio::Atmos]);
+        assert!(!movie.is_episode());
+
+        let daily = ReleaseName::parse("The Daily Show 2024 06 01 Guest Name 720p WEB h264-EDITH");
+        assert_eq!(daily.title, "The Daily Show");
+        assert_eq!(daily.air_date, NaiveDate::from_ymd_opt(2024, 6, 1));
+
+        let pack = ReleaseName::parse("Show.Name.S03.1080p.BluRay.x264-GROUP");
+        assert!(pack.is_season_pack());
+
+        let no_group = ReleaseName::parse("Movie.Title.2020.1080p.WEB-DL");
+        assert_eq!(no_group.group, None);
+        assert_eq!(no_group.source, Some(Source::WebDl));
+    }
+
+    #[test]
+    fn parse_dashed_title() {
+        let spaced = ReleaseName::parse("X-Men 2000 720p");
+        assert_eq!((spaced.title.as_str(), spaced.group), ("X Men", None));
+        assert_eq!((spaced.year, spaced.resolution), (Some(2000), Some(Resolution::R720p)));
+
+        let tagged = ReleaseName::parse("X-Men.2000.720p.BluRay.x264-GROUP [eztv]");
+        assert_eq!((tagged.title.as_str(), tagged.group.as_deref()), ("X Men", Some("GROUP")));
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, SystemTime};
+
+use crate::Error;
+
+/// Describes if and how failed API calls are retried by the [`Client`](crate::Client).
+///
+/// The delay before attempt `n + 1` is `initial_backoff * multiplier^(n - 1)`, capped at
+/// `max_backoff` and randomized by `jitter`. A `Retry-After` header sent by the indexer takes
+/// precedence over the computed delay, but is capped at `max_backoff` as well.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    respect_retry_after: bool,
+    retry_on: RetryOn,
+}
+
+/// Selects which errors are considered transient and therefore retried.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryOn {
+    /// Connection failures, timeouts and other errors below the HTTP layer
+    pub transport: bool,
+    /// HTTP status codes, e.g. 429 or 503
+    pub status_codes: Vec<u16>,
+    /// Newznab error codes, e.g. 900 (unknown error)
+    pub newznab_codes: Vec<u16>,
+}
+
+impl Default for RetryOn {
+    fn default() -> Self {
+        Self {
+            transport: true,
+            status_codes: vec![408, 429, 500, 502, 503, 504],
+            newznab_codes: vec![900],
+        }
+    }
+}
+
+impl RetryOn {
+    /// Returns `true` if `error` is considered transient.
+    pub fn matches(&self, error: &Error) -> bool {
+        match error {
+            Error::Http(_) => self.transport,
+            Error::HttpStatusCode(code, _) => self.status_codes.contains(code),
+            Error::NewznabError(e) => self.newznab_codes.contains(&e.code()),
+            _ => false,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            respect_retry_after: true,
+            retry_on: RetryOn::default(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A policy that never retries, every call is attempted exactly once.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Total number of attempts including the first one, `1` disables retrying.
+    pub fn max_attempts(mut self, value: u32) -> Self {
+        self.max_attempts = value.max(1);
+        self
+    }
+    pub fn initial_backoff(mut self, value: Duration) -> Self {
+        self.initial_backoff = value;
+        self
+    }
+    pub fn max_backoff(mut self, value: Duration) -> Self {
+        self.max_backoff = value;
+        self
+    }
+    pub fn multiplier(mut self, value: f64) -> Self {
+        self.multiplier = value.max(1.0);
+        self
+    }
+    /// Fraction (`0.0..=1.0`) by which each delay is randomly shortened or lengthened.
+    pub fn jitter(mut self, value: f64) -> Self {
+        self.jitter = value.clamp(0.0, 1.0);
+        self
+    }
+    pub fn respect_retry_after(mut self, value: bool) -> Self {
+        self.respect_retry_after = value;
+        self
+    }
+    pub fn retry_on(mut self, value: RetryOn) -> Self {
+        self.retry_on = value;
+        self
+    }
+
+    pub fn get_max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+    pub fn get_retry_on(&self) -> &RetryOn {
+        &self.retry_on
+    }
+
+    /// The delay before the next attempt, or `None` if `error` after `attempt` (1-based)
+    /// should be returned to the caller.
+    pub fn next_delay(&self, attempt: u32, error: &Error, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.retry_on.matches(error) {
+            return None;
+        }
+
+        if self.respect_retry_after {
+            if let Some(delay) = retry_after {
+                return Some(delay.min(self.max_backoff));
+            }
+        }
+
+        Some(self.backoff(attempt))
+    }
+
+    /// The jittered backoff after the given (1-based) attempt.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        let base = (self.initial_backoff.as_secs_f64() * exp).min(self.max_backoff.as_secs_f64());
+
+        let factor = 1.0 + self.jitter * (2.0 * random_unit() - 1.0);
+        Duration::from_secs_f64((base * factor).max(0.0))
+    }
+}
+
+/// Parses the value of a `Retry-After` header, either delta-seconds or an HTTP-date.
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let until = SystemTime::from(date);
+    Some(until.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// A random number in `0.0..1.0`, good enough to spread out retries.
+fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_nanos()
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(feature = "async")]
+pub(crate) async fn sleep(duration: Duration) {
+    async_std::task::sleep(duration).await;
+}
+
+#[cfg(feature = "sync")]
+pub(crate) fn sleep(duration: Duration) {
+    std::thread::sleep(duration);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::Error;
+    use crate::retry::{parse_retry_after, RetryPolicy};
+
+    #[test]
+    fn backoff_grows_and_is_capped() {
+        let policy = RetryPolicy::new()
+            .initial_backoff(Duration::from_secs(1))
+            .max_backoff(Duration::from_secs(5))
+            .jitter(0.0);
+
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(4), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn jitter_stays_in_bounds() {
+        let policy = RetryPolicy::new()
+            .initial_backoff(Duration::from_secs(10))
+            .jitter(0.5);
+
+        for _ in 0..100 {
+            let delay = policy.backoff(1);
+            assert!(delay >= Duration::from_secs(5) && delay <= Duration::from_secs(15));
+        }
+    }
+
+    #[test]
+    fn next_delay_respects_attempts_and_retry_after() {
+        let policy = RetryPolicy::new().max_attempts(2).jitter(0.0);
+        let err = Error::HttpStatusCode(503, String::new());
+
+        assert_eq!(policy.next_delay(1, &err, Some(Duration::from_secs(7))), Some(Duration::from_secs(7)));
+        assert_eq!(policy.next_delay(2, &err, None), None);
+        assert_eq!(policy.next_delay(1, &Error::HttpStatusCode(404, String::new()), None), None);
+        assert_eq!(RetryPolicy::none().next_delay(1, &err, None), None);
+    }
+
+    #[test]
+    fn next_delay_caps_retry_after() {
+        let policy = RetryPolicy::new().max_backoff(Duration::from_secs(30)).jitter(0.0);
+        let err = Error::HttpStatusCode(429, String::new());
+
+        assert_eq!(policy.next_delay(1, &err, Some(Duration::from_secs(3600))), Some(Duration::from_secs(30)));
+        assert_eq!(policy.next_delay(1, &err, Some(Duration::from_secs(10))), Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
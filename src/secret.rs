@@ -0,0 +1,149 @@
+use std::fmt::{Debug, Display};
+
+use maybe_http_client::HttpClientError;
+use serde::{Deserialize, Serialize};
+
+/// Replacement for secrets in `Debug`/`Display` output, logs and error messages
+pub const REDACTED: &str = "<redacted>";
+
+/// Query parameters that carry the api key or other credentials, `r` is used by the RSS
+/// feed. Names are compared ignoring case.
+const SECRET_PARAMS: [&str; 4] = ["apikey", "api_key", "r", "password"];
+
+/// Whether the query parameter `name` carries a secret, see `SECRET_PARAMS`.
+pub(crate) fn is_secret_param(name: &str) -> bool {
+    SECRET_PARAMS.iter().any(|p| p.eq_ignore_ascii_case(name))
+}
+
+/// An indexer api key that never shows up in `Debug` or `Display` output.
+///
+/// Use [`expose`](Self::expose) where the plain key is really needed. It is serialized as
+/// the plain key, so configs can be written back.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ApiKey(String);
+
+impl ApiKey {
+    pub fn new(value: impl AsRef<str>) -> Self {
+        Self(value.as_ref().to_string())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Replaces every occurrence of the key in `text`.
+    pub fn scrub(&self, text: &str) -> String {
+        self.scrub_with(text, REDACTED)
+    }
+
+    pub(crate) fn scrub_with(&self, text: &str, placeholder: &str) -> String {
+        if self.0.is_empty() {
+            text.to_string()
+        } else {
+            text.replace(&self.0, placeholder)
+        }
+    }
+}
+
+impl Debug for ApiKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ApiKey({})", REDACTED)
+    }
+}
+
+impl Display for ApiKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl From<&str> for ApiKey {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for ApiKey {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+/// Redacts the values of credential parameters (`apikey`, `api_key`, `r`, `password`) in a
+/// url or query string, also inside XML where `&` is escaped as `&amp;`.
+pub fn scrub_query(text: &str) -> String {
+    scrub_query_with(text, REDACTED)
+}
+
+pub(crate) fn scrub_query_with(text: &str, placeholder: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(idx) = rest.find(['?', '&']) {
+        out.push_str(&rest[..=idx]);
+        rest = &rest[idx + 1..];
+
+        let name_end = rest.find(['=', '&', '#', ' ']).unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        let param = name.strip_prefix("amp;").unwrap_or(name);
+        if rest[name_end..].starts_with('=') && is_secret_param(param) {
+            let value_end = rest[name_end + 1..]
+                .find(|c: char| c == '&' || c == '#' || c.is_whitespace() || c == '"' || c == '\'' || c == ')' || c == '<')
+                .map_or(rest.len(), |i| name_end + 1 + i);
+            out.push_str(name);
+            out.push('=');
+            out.push_str(placeholder);
+            rest = &rest[value_end..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Removes api keys from the request url of a transport error.
+pub(crate) fn scrub_http_error(error: HttpClientError) -> HttpClientError {
+    match error {
+        HttpClientError::Client(e) => HttpClientError::Client(scrub_reqwest_error(e)),
+        other => other,
+    }
+}
+
+/// Removes api keys from the url of a `reqwest` error, its `Display` output includes the url.
+fn scrub_reqwest_error(mut error: reqwest::Error) -> reqwest::Error {
+    if let Some(url) = error.url_mut() {
+        let pairs = url.query_pairs()
+            .map(|(k, v)| {
+                let secret = is_secret_param(&k);
+                (k.into_owned(), if secret { REDACTED.to_string() } else { v.into_owned() })
+            })
+            .collect::<Vec<_>>();
+        if !pairs.is_empty() {
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+    }
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::secret::{scrub_query, ApiKey};
+
+    #[test]
+    fn redacts_keys() {
+        let key = ApiKey::new("s3cr3t");
+        assert_eq!(format!("{:?} {}", key, key), "ApiKey(<redacted>) <redacted>");
+        assert_eq!(key.scrub("error for s3cr3t"), "error for <redacted>");
+
+        assert_eq!(
+            scrub_query("GET http://idx/api?t=search&apikey=s3cr3t&q=show failed"),
+            "GET http://idx/api?t=search&apikey=<redacted>&q=show failed"
+        );
+        assert_eq!(scrub_query("http://idx/rss?r=s3cr3t&dl=1"), "http://idx/rss?r=<redacted>&dl=1");
+        assert_eq!(scrub_query("http://idx/api?q=r&rage=1"), "http://idx/api?q=r&rage=1");
+        assert_eq!(
+            scrub_query("<link>http://idx/getnzb/1.nzb&amp;i=1&amp;r=s3cr3t</link>"),
+            "<link>http://idx/getnzb/1.nzb&amp;i=1&amp;r=<redacted></link>"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Searches that own their client, so they can be moved to other tasks, stored as a
+//! [`SearchCursor`] and resumed later.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{Client, Error};
+use crate::common::Function;
+use crate::common::models::{ActiveSearchResult, RssItem, SearchCursor};
+
+/// Cancels a [`SearchSession`] from another task, see [`SearchSession::cancel_handle`].
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// An [`ActiveSearchResult`] together with the shared [`Client`] of its indexer.
+///
+/// Once cancelled every further page fails with [`Error::Cancelled`], a request already
+/// underway still completes.
+#[derive(Debug)]
+pub struct SearchSession {
+    client: Arc<Client>,
+    result: ActiveSearchResult,
+    cancel: CancelHandle,
+}
+
+#[cfg_attr(target_arch = "wasm32", maybe_async::maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async::maybe_async)]
+impl SearchSession {
+    /// Runs the search `f` and keeps its first page.
+    #[maybe_async::maybe_async(AFIT)]
+    pub async fn start(client: Arc<Client>, f: Function) -> Result<Self, Error> {
+        let result = client.search(f).await?;
+        Self::from_result(client, result)
+    }
+
+    /// Continues `result` with the `client` of the indexer that returned it.
+    pub fn from_result(client: Arc<Client>, result: ActiveSearchResult) -> Result<Self, Error> {
+        if result.api_url != client.get_api_url() {
+            return Err(Error::IndexerMismatch {
+                expected: result.api_url,
+                actual: client.get_api_url().to_string(),
+            });
+        }
+        Ok(Self { client, result, cancel: CancelHandle::default() })
+    }
+
+    /// See [`ActiveSearchResult::from_cursor`]
+    pub fn resume(client: Arc<Client>, cursor: SearchCursor) -> Result<Self, Error> {
+        Self::from_result(client, ActiveSearchResult::from_cursor(cursor))
+    }
+
+    pub fn client(&self) -> &Arc<Client> {
+        &self.client
+    }
+
+    pub fn result(&self) -> &ActiveSearchResult {
+        &self.result
+    }
+
+    pub fn into_result(self) -> ActiveSearchResult {
+        self.result
+    }
+
+    pub fn cursor(&self) -> SearchCursor {
+        self.result.cursor()
+    }
+
+    /// A handle that cancels this session, it can be sent to other tasks.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub fn has_more(&self) -> bool {
+        self.result.has_more() && !self.is_cancelled()
+    }
+
+    /// See [`ActiveSearchResult::remaining`]
+    pub fn remaining(&self) -> Option<u64> {
+        self.result.remaining()
+    }
+
+    /// Fetches the next page and returns its new items, `None` once the results ended.
+    #[maybe_async::maybe_async(AFIT)]
+    pub async fn next_page(&mut self) -> Result<Option<&[RssItem]>, Error> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        let known = self.result.items.len();
+        match self.result.next_page(&self.client).await? {
+            Some(_) => Ok(Some(&self.result.items[known..])),
+            None => Ok(None),
+        }
+    }
+
+    /// Fetches the remaining pages and returns all items.
+    #[maybe_async::maybe_async(AFIT)]
+    pub async fn all(&mut self) -> Result<&[RssItem], Error> {
+        while self.next_page().await?.is_some() {}
+        Ok(&self.result.items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::Error;
+    use crate::common::Function;
+    use crate::common::models::{SearchCursor, SearchKind, SearchQuery};
+    use crate::mock::{MockIndexer, MockRelease};
+    use crate::session::SearchSession;
+
+    fn indexer() -> MockIndexer {
+        MockIndexer::new()
+            .limits(10, 10)
+            .releases((0..25).map(|i| MockRelease::new(format!("Show.S01E{:02}", i))))
+    }
+
+    #[test]
+    fn session_is_send() {
+        fn assert_send<T: Send + Sync + 'static>() {}
+        assert_send::<SearchSession>();
+    }
+
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn resumes_from_cursor() {
+        let indexer = indexer();
+        let client = Arc::new(indexer.client());
+
+        let session = SearchSession::start(client.clone(), Function::TvSearch(SearchQuery::new("show").build())).await.unwrap();
+        assert_eq!((session.result().items.len(), session.remaining()), (10, Some(15)));
+
+        let json = serde_json::to_string(&session.cursor()).unwrap();
+        let cursor = serde_json::from_str::<SearchCursor>(&json).unwrap();
+        assert_eq!((cursor.kind, cursor.pages.len()), (SearchKind::TvSearch, 1));
+
+        let mut resumed = SearchSession::resume(client.clone(), cursor).unwrap();
+        let next = resumed.next_page().await.unwrap().unwrap().to_vec();
+        assert_eq!(next[0].title(), Some("Show.S01E10"));
+        let rest = resumed.all().await.unwrap();
+        assert_eq!(rest.len(), 15);
+        assert!(!resumed.has_more());
+
+        let other = MockIndexer::new().client_builder().url("http://other.example").to_client();
+        let wrong = SearchSession::resume(Arc::new(other), resumed.cursor());
+        assert!(matches!(wrong, Err(Error::IndexerMismatch { .. })));
+
+        let caps = SearchSession::start(client, Function::Caps).await;
+        assert!(matches!(caps, Err(Error::NotASearch(_))));
+    }
+
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn cancels() {
+        let indexer = indexer();
+        let mut session = SearchSession::start(Arc::new(indexer.client()), SearchQuery::new("show").into()).await.unwrap();
+
+        let handle = session.cancel_handle();
+        handle.cancel();
+        assert!(!session.has_more());
+        let cancelled = session.next_page().await;
+        assert!(matches!(cancelled, Err(Error::Cancelled)));
+        assert_eq!(session.result().next_offset(), 10);
+        assert_eq!(indexer.requests().len(), 2);
+    }
+
+    #[cfg(not(feature = "sync"))]
+    #[async_std::test]
+    async fn moves_across_tasks() {
+        let mut session = SearchSession::start(Arc::new(indexer().client()), SearchQuery::new("show").into()).await.unwrap();
+        let items = async_std::task::spawn(async move { session.all().await.map(<[_]>::len) }).await.unwrap();
+        assert_eq!(items, 25);
+    }
+}
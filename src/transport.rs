@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use maybe_http_client::{HttpClient, HttpClientError};
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+use crate::secret::scrub_http_error;
+
+/// A raw HTTP response as seen by the [`Client`](crate::Client).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransportResponse {
+    pub status: u16,
+    /// Header names are lowercase
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: String,
+}
+
+impl TransportResponse {
+    /// A `200 OK` response without headers.
+    pub fn ok(body: impl Into<String>) -> Self {
+        Self {
+            status: 200,
+            headers: BTreeMap::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn with_status(mut self, value: u16) -> Self {
+        self.status = value;
+        self
+    }
+
+    pub fn with_header(mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.headers.insert(name.as_ref().to_lowercase(), value.as_ref().to_string());
+        self
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(String::as_str)
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Sends the HTTP requests of a [`Client`](crate::Client).
+///
+/// Implement this to route requests through a proxy, a custom TLS setup, a mock or an
+/// instrumented HTTP stack and pass it to
+/// [`ClientBuilder::transport`](crate::ClientBuilder::transport). Responses with an error
+/// status are returned as `Ok`, only failures to get a response at all are errors.
+#[cfg_attr(target_arch = "wasm32", maybe_async::maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async::maybe_async)]
+pub trait Transport: Debug + Send + Sync {
+    /// Performs a `GET` request of `url` with `query` appended as query string.
+    async fn get(&self, url: &str, query: &BTreeMap<String, String>) -> Result<TransportResponse, Error>;
+}
+
+/// The default transport
+#[cfg_attr(target_arch = "wasm32", maybe_async::maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async::maybe_async)]
+impl Transport for HttpClient {
+    async fn get(&self, url: &str, query: &BTreeMap<String, String>) -> Result<TransportResponse, Error> {
+        let query = query.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+
+        match HttpClient::get(self, url, None, &query).await {
+            Ok(body) => Ok(TransportResponse::ok(body)),
+            Err(HttpClientError::StatusCode(response)) => {
+                let status = response.status().as_u16();
+                let headers = response.headers().iter()
+                    .filter_map(|(name, value)| Some((name.as_str().to_lowercase(), value.to_str().ok()?.to_string())))
+                    .collect();
+                let body = String::from_utf8_lossy(&response.bytes().await.unwrap_or_default()).to_string();
+                Ok(TransportResponse { status, headers, body })
+            }
+            Err(e) => Err(Error::Http(Box::new(scrub_http_error(e)))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    use crate::{ClientBuilder, Error};
+    use crate::common::Function;
+    use crate::common::models::SearchParameters;
+    use crate::transport::{Transport, TransportResponse};
+
+    const CAPS: &str = r#"<caps><server title="Test"/><limits max="50" default="50"/><searching><search available="yes" supportedParams="q"/></searching><categories><category id="5000" name="TV"/></categories></caps>"#;
+    const SEARCH: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:newznab="http://www.newznab.com/DTD/2010/feeds/attributes/">
+<channel><title>Test</title><link>http://test</link><description>Test</description>
+<newznab:response offset="0" total="1"/>
+<item><title>Show.S01E01</title><guid>1</guid><newznab:attr name="size" value="100"/></item>
+</channel></rss>"#;
+
+    #[derive(Debug, Default)]
+    struct Recording {
+        requests: Mutex<Vec<BTreeMap<String, String>>>,
+    }
+
+    #[cfg_attr(target_arch = "wasm32", maybe_async::maybe_async(?Send))]
+    #[cfg_attr(not(target_arch = "wasm32"), maybe_async::maybe_async)]
+    impl Transport for Recording {
+        async fn get(&self, _url: &str, query: &BTreeMap<String, String>) -> Result<TransportResponse, Error> {
+            self.requests.lock().unwrap().push(query.clone());
+            match query.get("t").map(String::as_str) {
+                Some("caps") => Ok(TransportResponse::ok(CAPS)),
+                Some("search") => Ok(TransportResponse::ok(SEARCH)),
+                _ => Ok(TransportResponse::ok("").with_status(404)),
+            }
+        }
+    }
+
+    #[maybe_async::test(
+        feature="sync",
+        async(all(not(feature="sync"), feature="async"), async_std::test),
+    )]
+    async fn requests_go_through_transport() {
+        let transport = Arc::new(Recording::default());
+        let client = ClientBuilder::new()
+            .url("http://test")
+            .api_token("key")
+            .transport(transport.clone())
+            .to_client();
+
+        assert_eq!(client.known_caps().limits.max(), 50);
+
+        let result = client.search(Function::Search(SearchParameters {
+            q: "show".to_string(),
+            limit: None,
+            offset: None,
+            params: None,
+        })).await.unwrap();
+        assert_eq!(result.items.len(), 1);
+
+        {
+            let requests = transport.requests.lock().unwrap();
+            assert_eq!(requests.len(), 2);
+            assert_eq!(requests[1].get("limit").map(String::as_str), Some("50"));
+            assert_eq!(requests[1].get("apikey").map(String::as_str), Some("key"));
+        }
+
+        match client.function(Function::Register { email: "a@b.c".to_string() }, Default::default()).await {
+            Err(Error::HttpStatusCode(404, _)) => {}
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+    }
+}